@@ -0,0 +1,184 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::block::Title;
+use ratatui::widgets::{Block, Paragraph};
+use style::Styled;
+
+use crate::app::{App, CurrentFrame};
+use crate::note::{Note, Priority, Task};
+use crate::journal;
+use crate::query::parse_day;
+use crate::settings::{theme, Keymap};
+use crate::traits::ThisFrame;
+use crate::utils::{render_scrollbar, RcRc};
+
+#[derive(Debug, Clone)]
+pub struct AgendaEntry {
+    pub note: RcRc<Note>,
+    pub task: Task,
+}
+
+// open tasks across the vault, soonest due first
+#[derive(Debug, Default, Clone)]
+pub struct Agenda {
+    pub entries: Vec<AgendaEntry>,
+    pub index: usize,
+    pub scroll: usize,
+    pub is_active: bool,
+}
+
+impl ThisFrame for Agenda {
+    fn get_instructions(&self, _keymap: &Keymap) -> Title<'_> {
+        Title::from(Line::from(vec![
+            " Back ".into(),
+            "<esc>".blue().bold(),
+            " Scroll Up ".into(),
+            "<UP>".blue().bold(),
+            " Scroll Down ".into(),
+            "<DOWN>".blue().bold(),
+            " Open Task ".into(),
+            "<return>".blue().bold(),
+        ]))
+    }
+
+    fn get_type(&self) -> String {
+        "Agenda".to_string()
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => {
+                app.agenda.is_active = false;
+                app.note_list.is_active = true;
+                app.current_frame = CurrentFrame::List;
+            }
+            KeyCode::Up => {
+                if self.index == 0 {
+                    app.agenda.index = self.entries.len().saturating_sub(1);
+                } else {
+                    app.agenda.index = self.index - 1;
+                }
+            }
+            KeyCode::Down => {
+                if self.index + 1 >= self.entries.len() {
+                    app.agenda.index = 0;
+                } else {
+                    app.agenda.index = self.index + 1;
+                }
+            }
+            KeyCode::Enter => {
+                let Some(entry) = self.entries.get(self.index) else {
+                    return;
+                };
+                app.open(entry.note.to_owned(), (entry.task.row, 0));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Agenda {
+    pub fn collect(notes: &[RcRc<Note>]) -> Self {
+        let mut entries: Vec<AgendaEntry> = notes
+            .iter()
+            .flat_map(|note| {
+                let tasks = note.borrow().tasks.clone().unwrap_or_default();
+                tasks
+                    .into_iter()
+                    .filter(|task| !task.done)
+                    .map(|task| AgendaEntry {
+                        note: note.clone(),
+                        task,
+                    })
+            })
+            .collect();
+        // undated last, and a task without a priority just below medium
+        entries.sort_by_cached_key(|entry| {
+            let task = &entry.task;
+            (
+                task.due.is_none(),
+                task.due.clone(),
+                task.priority.unwrap_or(Priority::Medium),
+                task.priority.is_none(),
+                entry.note.borrow().title.clone(),
+                task.row,
+            )
+        });
+        Agenda {
+            entries,
+            index: 0,
+            scroll: 0,
+            is_active: true,
+        }
+    }
+
+    // the heading each entry sits under
+    fn groups(&self) -> Vec<String> {
+        let today = journal::today();
+        self.entries
+            .iter()
+            .map(|entry| {
+                let Some(due) = &entry.task.due else {
+                    return "No due date".to_string();
+                };
+                match parse_day(due) {
+                    Some(day) if day < today => "Overdue".to_string(),
+                    Some(day) if day == today => "Today".to_string(),
+                    Some(day) if today.succ_opt() == Some(day) => "Tomorrow".to_string(),
+                    _ => due.clone(),
+                }
+            })
+            .collect()
+    }
+
+    // screen row of the selected task, counting the group headings above it
+    pub fn selected_row(&self) -> usize {
+        let groups = self.groups();
+        let headings = (0..=self.index.min(groups.len().saturating_sub(1)))
+            .filter(|i| *i == 0 || groups[*i] != groups[i - 1])
+            .count();
+        self.index + headings
+    }
+}
+
+impl Widget for &Agenda {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let title = Title::from(format!(" Agenda ({}) ", self.entries.len()).fg(theme().text).bold());
+        let block = Block::bordered()
+            .title(title.alignment(Alignment::Center))
+            .border_set(symbols::border::ROUNDED)
+            .set_style(theme().active);
+        let groups = self.groups();
+        let mut lines: Vec<Line> = vec![];
+        for (count, entry) in self.entries.iter().enumerate() {
+            if count == 0 || groups[count] != groups[count - 1] {
+                let colour = if groups[count] == "Overdue" {
+                    theme().error
+                } else {
+                    theme().heading
+                };
+                lines.push(Line::from(groups[count].clone().fg(colour).bold()));
+            }
+            let colour = if self.index == count {
+                theme().selected
+            } else {
+                theme().text
+            };
+            lines.push(Line::from(vec![
+                "  ☐ ".fg(theme().task),
+                entry.task.text.clone().fg(colour),
+                format!("  {}", entry.note.borrow().title).dim(),
+            ]));
+        }
+        let rows = lines.len();
+        Paragraph::new(lines)
+            .left_aligned()
+            .block(block)
+            .scroll((self.scroll as u16, 0))
+            .render(area, buf);
+        render_scrollbar(area, buf, self.scroll, rows);
+    }
+}
@@ -1,24 +1,38 @@
-use std::{
-    fmt::{self, Display}, io::{self, Result}
-};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use std::{fmt, io};
 
-use crossterm::event::{self, Event, KeyEvent, KeyEventKind};
+use chrono::{Local, NaiveDate};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{
     prelude::*,
-    widgets::{
-        block::{Position, Title},
-        Block, BorderType, Borders, Paragraph,
-    },
+    widgets::{block::Title, Block, Paragraph},
 };
-use symbols::border;
-use text::{ToLine, ToSpan};
+use text::ToSpan;
 
 use crate::{
+    agenda::Agenda,
+    backlinks::Backlinks,
+    error::{Error, Result},
+    file_reader::{changed_on_disk, get_notes, parse_file, read_file, write_file, OnDisk},
+    finder::Finder,
+    history::{self, History},
+    journal::{self, Period},
     list::MyList,
-    note::{self, Note},
+    note::{Note, NoteKey},
+    popup::Popup,
+    settings::{theme, Settings},
+    swap,
+    tags::TagTree,
+    templates::{self, TemplateForm},
     traits::ThisFrame,
-    tui::Tui,
-    utils::RcRc,
+    tui::{self, Tui},
+    utils::{display_width, rc_rc, scroll_into_view, RcRc},
+    watcher::Watcher,
+    wrap::{cursor_row, cursor_x, visual_rows},
 };
 
 #[derive(Debug, Clone, Default)]
@@ -33,6 +47,9 @@ pub enum InputMode {
 pub enum CurrentFrame {
     Note,
     List,
+    Backlinks,
+    Tags,
+    Agenda,
 }
 impl fmt::Display for CurrentFrame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -40,11 +57,49 @@ impl fmt::Display for CurrentFrame {
     }
 }
 
+// what to carry on with once unsaved edits have been saved or thrown away
+#[derive(Debug, Clone)]
+pub enum Pending {
+    Quit,
+    Open(RcRc<Note>, (usize, usize)),
+    New(PathBuf),
+    // between the whole vault and the local notes folder
+    SwitchList,
+}
+
+// how long the run loop waits for a key before checking on autosave
+const TICK: Duration = Duration::from_millis(250);
+// a pause in typing this long writes the open note's swap file
+const SWAP_DELAY: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 pub struct App {
+    pub settings: Settings,
     pub current_frame: CurrentFrame,
     pub note: RcRc<Note>,
     pub note_list: MyList,
+    pub backlinks: Backlinks,
+    pub tag_tree: TagTree,
+    pub agenda: Agenda,
+    pub popup: Option<Popup>,
+    pub history: HashMap<NoteKey, History>,
+    // text rows visible in the note and list panes at the last draw, for paging
+    pub note_height: usize,
+    pub note_width: usize,
+    pub list_height: usize,
+    pub wrap: bool,
+    // first half of a two key command such as gg
+    pub pending_key: Option<char>,
+    // set by a key handler, the run loop hands the terminal over to $EDITOR
+    pub open_in_editor: bool,
+    // the text search a note was opened from, highlighted and stepped through with n/N
+    pub highlight: Option<String>,
+    // the last key press while the open note had unsaved edits, autosave waits on it
+    pub last_edit: Option<Instant>,
+    // the swap file holds the edits made up to last_edit
+    pub swapped: bool,
+    // files changed outside noter, None when the vault couldn't be watched
+    pub watcher: Option<Watcher>,
     pub input_mode: bool,
     pub cursor_row: usize,
     pub cursor_column: usize,
@@ -54,81 +109,198 @@ pub struct App {
 impl App {
     pub fn run(&mut self, terminal: &mut Tui) -> io::Result<()> {
         while !self.exit {
-            terminal.draw(|frame| self.render_frame(frame)).unwrap();
-            self.handle_events().unwrap();
+            terminal.draw(|frame| self.render_frame(frame))?;
+            if event::poll(TICK)? {
+                self.handle_events()?;
+            }
+            self.sync_files();
+            self.autosave();
+            if self.open_in_editor {
+                self.open_in_editor = false;
+                self.edit_externally(terminal)?;
+            }
         }
         Ok(())
     }
 
-    fn render_frame(&self, frame: &mut Frame) {
+    // suspend the TUI while $VISUAL or $EDITOR edits the open note, then read back its file
+    fn edit_externally(&mut self, terminal: &mut Tui) -> io::Result<()> {
+        let root = self.note_list.root();
+        let path = self.note.borrow().file_path(&root, &self.settings.extension);
+        tui::restore()?;
+        let status = launch_editor(&path);
+        *terminal = tui::init()?;
+        terminal.clear()?;
+        if let Err(err) = status {
+            self.report(err);
+            return Ok(());
+        }
+        let parsed = match read_file(&path).and_then(|text| parse_file(text, &path, &root)) {
+            Ok(parsed) => parsed.borrow().clone(),
+            Err(err) => {
+                self.report(err);
+                return Ok(());
+            }
+        };
+        let note = self.note.clone();
+        self.reload(&note, parsed);
+        self.refresh_backlinks();
+        Ok(())
+    }
+
+    // update in place, the list and any backlinks hold the same note
+    fn reload(&mut self, note: &RcRc<Note>, parsed: Note) {
+        let mut target = note.borrow_mut();
+        let (mode, scroll, is_active) = (target.mode.clone(), target.scroll, target.is_active);
+        let key = target.key;
+        *target = parsed;
+        target.key = key;
+        target.mode = mode;
+        target.scroll = scroll;
+        target.is_active = is_active;
+        if Rc::ptr_eq(note, &self.note) {
+            self.cursor_row = self.cursor_row.min(target.row_count().saturating_sub(1));
+            self.cursor_column = target.clamp_column(self.cursor_row, self.cursor_column);
+        }
+    }
+
+    // after a pause in typing, keep a swap file of the open note and save it if autosave is on
+    fn autosave(&mut self) {
+        let Some(idle) = self.last_edit.map(|last_edit| last_edit.elapsed()) else {
+            return;
+        };
+        {
+            let note = self.note.borrow();
+            // an untitled note, or one having its title typed, has no file to go to yet
+            if !note.edited || note.title.is_empty() || matches!(note.mode, InputMode::EditTitle) {
+                return;
+            }
+        }
+        if self.settings.autosave.is_some_and(|delay| idle >= Duration::from_millis(delay)) {
+            let note = self.note.clone();
+            self.save(&note);
+        } else if !self.swapped && idle >= SWAP_DELAY {
+            self.swapped = true;
+            let written = swap::write(
+                &self.note_list.root(),
+                &self.settings.extension,
+                &self.note.borrow(),
+            );
+            if let Err(err) = written {
+                self.report(err);
+            }
+        }
+    }
+
+    // takes &mut self to keep the scroll offsets following the cursor at the current size
+    fn render_frame(&mut self, frame: &mut Frame) {
         let layout = Layout::horizontal(Constraint::from_percentages([15, 85]));
         let vertical = Layout::vertical(Constraint::from_percentages([98, 2]));
         let [main_area, instructions] = vertical.areas(frame.area());
 
         let [list_area, note_area] = layout.areas(main_area);
-        let note = self.note.borrow_mut().clone();
-        frame.render_widget(self, instructions);
+        frame.render_widget(&*self, instructions);
 
+        self.list_height = list_area.height.saturating_sub(2) as usize;
+        // keep the whole entry on screen, snippet row included
+        let row = self.note_list.index * self.note_list.entry_height();
+        let scroll = scroll_into_view(
+            self.note_list.scroll,
+            row + self.note_list.entry_height() - 1,
+            self.list_height,
+        );
+        self.note_list.scroll = scroll_into_view(scroll, row, self.list_height);
         let index = self.note_list.index;
         frame.render_stateful_widget(
             &self.note_list.clone(),
             list_area,
             &mut (index, self.note_list.notes.clone()),
         );
-        if self.note_list.is_active {
+        if self.tag_tree.is_active {
+            self.tag_tree.scroll = scroll_into_view(
+                self.tag_tree.scroll,
+                self.tag_tree.index,
+                note_area.height.saturating_sub(2) as usize,
+            );
+            frame.render_widget(&self.tag_tree, note_area);
+        } else if self.agenda.is_active {
+            self.agenda.scroll = scroll_into_view(
+                self.agenda.scroll,
+                self.agenda.selected_row(),
+                note_area.height.saturating_sub(2) as usize,
+            );
+            frame.render_widget(&self.agenda, note_area);
+        } else if self.note_list.is_active {
             if self.note_list.is_search {
+                let search = self.note_list.search.as_deref().unwrap_or("");
                 frame.set_cursor_position(layout::Position::new(
-                    self.cursor_column as u16 + list_area.x + 8, // 8 for search:
+                    display_width(search, self.cursor_column) as u16 + list_area.x + 8, // 8 for search:
                     instructions.y, // use instructions as has same vertical as search
                 ));
-                frame.render_widget(
-                    &self
-                        .note_list
-                        .filter_list(self.note_list.search.clone())
-                        .unwrap()
-                        .get(self.note_list.index)
-                        .unwrap()
-                        .borrow_mut()
-                        .clone(),
-                    note_area,
-                );
+                match self.note_list.results().get(self.note_list.index) {
+                    Some(selected) => frame.render_widget(&self.preview(selected), note_area),
+                    None => frame.render_widget(Block::bordered().fg(theme().text), note_area),
+                }
+            } else if let Some(selected) = self.note_list.selected_note() {
+                frame.render_widget(&self.preview(&selected), note_area)
             } else {
-                frame.render_widget(
-                    &self
-                        .note_list
-                        .notes
-                        .get(self.note_list.index)
-                        .unwrap()
-                        .borrow_mut()
-                        .clone(),
-                    note_area,
-                )
+                frame.render_widget(Block::bordered().fg(theme().text), note_area)
             }
         } else {
-            frame.render_widget(&self.note.borrow_mut().clone(), note_area);
-
-            match self.note.borrow_mut().clone().mode {
+            let [note_area, backlinks_area] =
+                Layout::horizontal(Constraint::from_percentages([75, 25])).areas(note_area);
+            self.note_height = note_area.height.saturating_sub(2) as usize;
+            self.note_width = note_area.width.saturating_sub(2) as usize;
+            let rows = visual_rows(
+                &self.note.borrow().text,
+                self.wrap.then_some(self.note_width),
+            );
+            let visual = cursor_row(&rows, self.cursor_row, self.cursor_column);
+            {
+                let mut note = self.note.borrow_mut();
+                note.scroll = scroll_into_view(note.scroll, visual, self.note_height);
+            }
+            let note = self.preview(&self.note);
+            frame.render_widget(&note, note_area);
+            frame.render_widget(&self.backlinks, backlinks_area);
+            match note.mode {
+                _ if self.backlinks.is_active => {}
                 InputMode::EditTitle => {
+                    let title_width = display_width(&note.title, usize::MAX);
                     frame.set_cursor_position(layout::Position::new(
-                        self.cursor_column as u16
+                        display_width(&note.title, self.cursor_column) as u16
                             + note_area.x
                             + (note_area.width as f64 / 2.).floor() as u16
-                            - (note.title.len() as f64 / 2.).ceil() as u16,
+                            - (title_width as f64 / 2.).ceil() as u16,
                         0,
                     ));
                 }
-                _ => frame.set_cursor_position(layout::Position::new(
-                    self.cursor_column as u16 + note_area.x + 1,
-                    self.cursor_row.saturating_add(1) as u16,
-                )),
+                _ => {
+                    let line = note.text.split('\n').nth(self.cursor_row).unwrap_or("");
+                    let x = rows
+                        .get(visual)
+                        .map_or(0, |row| cursor_x(line, *row, self.cursor_column));
+                    frame.set_cursor_position(layout::Position::new(
+                        x as u16 + note_area.x + 1,
+                        (visual - note.scroll) as u16 + note_area.y + 1,
+                    ))
+                }
             }
         }
+        if let Some(popup) = &self.popup {
+            frame.render_widget(popup, main_area);
+        }
     }
 
     fn handle_events(&mut self) -> io::Result<()> {
-        match event::read().unwrap() {
+        match event::read()? {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                self.handle_key_event(key_event)
+                self.handle_key_event(key_event);
+                // every key while there are unsaved edits puts autosave off a little longer
+                if self.note.borrow().edited {
+                    self.last_edit = Some(Instant::now());
+                    self.swapped = false;
+                }
             }
             _ => {}
         };
@@ -136,6 +308,20 @@ impl App {
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if let Some(popup) = self.popup.clone() {
+            popup.handle_key_event(self, key_event);
+            return;
+        }
+        // the finder opens from any frame, except while typing into a note
+        let typing = matches!(self.current_frame, CurrentFrame::Note)
+            && !matches!(self.note.borrow().mode, InputMode::Normal);
+        if key_event.code == KeyCode::Char(self.settings.keymap.finder)
+            && key_event.modifiers.contains(KeyModifiers::CONTROL)
+            && !typing
+        {
+            self.popup = Some(Popup::Finder(Finder::new(self.note_list.notes.clone())));
+            return;
+        }
         match self.current_frame {
             CurrentFrame::Note => {
                 let mut note = self.note.borrow_mut().clone();
@@ -145,12 +331,494 @@ impl App {
                 let mut list = self.note_list.clone();
                 list.handle_key_event(self, key_event);
             }
+            CurrentFrame::Backlinks => {
+                let mut backlinks = self.backlinks.clone();
+                backlinks.handle_key_event(self, key_event);
+            }
+            CurrentFrame::Tags => {
+                let mut tag_tree = self.tag_tree.clone();
+                tag_tree.handle_key_event(self, key_event);
+            }
+            CurrentFrame::Agenda => {
+                let mut agenda = self.agenda.clone();
+                agenda.handle_key_event(self, key_event);
+            }
+        };
+    }
+
+    // a copy of a note to draw, carrying the session's view options
+    fn preview(&self, note: &RcRc<Note>) -> Note {
+        let mut note = note.borrow().clone();
+        note.wrap = self.wrap;
+        note.highlight = if self.note_list.is_search && self.note_list.full_text {
+            self.note_list.search.clone()
+        } else {
+            self.highlight.clone()
+        };
+        note
+    }
+
+    // recompute the backlinks pane for the open note, call with no borrow of it held
+    pub fn refresh_backlinks(&mut self) {
+        let id = self.note.borrow().id();
+        let is_active = self.backlinks.is_active;
+        self.backlinks = Backlinks::collect(&self.note_list, &id);
+        self.backlinks.is_active = is_active;
+    }
+
+    // watch the folder the list was loaded from, called again whenever that changes
+    pub fn watch(&mut self) -> Result<()> {
+        self.watcher = None;
+        self.watcher = Some(Watcher::new(&self.note_list.root(), &self.settings.templates)?);
+        Ok(())
+    }
+
+    // bring the list up to date with files added, edited or removed outside noter
+    fn sync_files(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
         };
+        let extension = self.settings.extension.clone();
+        let changed = watcher.changed(&extension);
+        if changed.is_empty() {
+            return;
+        }
+        let root = watcher.root.clone();
+        let mut errors = vec![];
+        for path in changed {
+            let at = |note: &RcRc<Note>| note.borrow().file_path(&root, &extension) == path;
+            let index = self.note_list.notes.iter().position(at);
+            let existing = index.map(|index| self.note_list.notes[index].clone());
+            if !path.is_file() {
+                let Some(note) = existing else {
+                    continue;
+                };
+                // unsaved edits keep the note around, saving puts the file back
+                let edited = note.borrow().edited;
+                if edited || Rc::ptr_eq(&note, &self.note) {
+                    note.borrow_mut().changed = true;
+                }
+                if let (Some(index), false) = (index, edited) {
+                    self.note_list.notes.remove(index);
+                }
+                continue;
+            }
+            let text = match read_file(&path) {
+                Ok(text) => text,
+                Err(err) => {
+                    errors.push(err);
+                    continue;
+                }
+            };
+            if let Some(note) = &existing {
+                let note = note.borrow();
+                // noter's own writes come back as events too
+                if note.disk.as_ref().is_some_and(|disk| disk.contents == text) {
+                    continue;
+                }
+            }
+            match existing {
+                Some(note) if note.borrow().edited => note.borrow_mut().changed = true,
+                _ => match parse_file(text, &path, &root) {
+                    Ok(parsed) => match existing {
+                        Some(note) => {
+                            let parsed = parsed.borrow().clone();
+                            self.reload(&note, parsed);
+                            // the open note's text moved under the cursor, say why
+                            if Rc::ptr_eq(&note, &self.note) {
+                                note.borrow_mut().changed = true;
+                            }
+                        }
+                        None => self.note_list.notes.push(parsed),
+                    },
+                    Err(err) => errors.push(err),
+                },
+            }
+        }
+        self.refresh_backlinks();
+        if self.tag_tree.is_active {
+            self.tag_tree.entries = TagTree::build(&self.note_list.notes, &self.tag_tree.collapsed);
+            self.tag_tree.index =
+                self.tag_tree.index.min(self.tag_tree.entries.len().saturating_sub(1));
+        }
+        if self.agenda.is_active {
+            let index = self.agenda.index;
+            self.agenda = Agenda::collect(&self.note_list.notes);
+            self.agenda.index = index.min(self.agenda.entries.len().saturating_sub(1));
+        }
+        self.report_all(errors);
+    }
+
+    // take on freshly loaded settings and load the vault they point at
+    pub fn load_settings(&mut self, settings: Settings) {
+        settings.publish_theme();
+        self.wrap = settings.wrap;
+        self.settings = settings;
+        match MyList::new(&self.settings) {
+            Ok((note_list, skipped)) => {
+                self.note_list = note_list;
+                let mut skipped = skipped;
+                if let Err(err) = self.watch() {
+                    skipped.push(err);
+                }
+                let swaps = swap::find(&self.note_list.root(), &self.settings.extension);
+                if swaps.is_empty() {
+                    self.report_all(skipped);
+                } else {
+                    // recovery comes first, notes that failed to load are listed after it
+                    let errors = skipped.iter().map(|err| err.to_string()).collect();
+                    self.popup = Some(Popup::Recover { swaps, errors });
+                }
+            }
+            Err(err) => self.report(err),
+        }
+    }
+
+    // open the journal note for the period holding the date, creating it if it's new
+    pub fn open_journal(&mut self, period: Period, date: NaiveDate) {
+        let journal = &self.settings.journal;
+        let title = period.title(date, journal);
+        let folder = PathBuf::from(&journal.folder);
+        let existing = self
+            .note_list
+            .notes
+            .iter()
+            .find(|note| {
+                let note = note.borrow();
+                note.folder == folder && note.title == title
+            })
+            .cloned();
+        if let Some(note) = existing {
+            self.open(note, (0, 0));
+            return;
+        }
+        let root = self.note_list.root();
+        match journal::create(&root, &self.settings.extension, journal, period, date) {
+            Ok((note, cursor)) => {
+                self.note_list.notes.push(note.clone());
+                self.open(note, cursor);
+            }
+            Err(err) => self.report(err),
+        }
+    }
+
+    // make a note from a filled in template and open it where the template put the cursor
+    pub fn create_from_template(&mut self, form: &TemplateForm) {
+        let root = self.note_list.root();
+        let when = Local::now().naive_local();
+        let created = templates::create(
+            &root,
+            &self.settings.extension,
+            &form.folder,
+            form.title(),
+            &form.text,
+            when,
+            &form.values(),
+        );
+        match created {
+            Ok((note, cursor)) => {
+                self.note_list.notes.push(note.clone());
+                self.open(note, cursor);
+            }
+            Err(err) => self.report(err),
+        }
+    }
+
+    // a new untitled note in the folder, saved once it has a title
+    pub fn new_note(&mut self, folder: PathBuf) {
+        self.leave(Pending::New(folder));
+    }
+
+    // switch the note frame to a note from the list
+    pub fn open(&mut self, note: RcRc<Note>, cursor: (usize, usize)) {
+        self.leave(Pending::Open(note, cursor));
     }
 
     pub fn exit(&mut self) {
         self.exit = true;
     }
+
+    pub fn quit(&mut self) {
+        self.leave(Pending::Quit);
+    }
+
+    pub fn switch_list(&mut self) {
+        self.leave(Pending::SwitchList);
+    }
+
+    // quit or switch notes, asking first when that would leave unsaved edits behind
+    fn leave(&mut self, then: Pending) {
+        let unsaved = match &then {
+            // a new list drops the notes holding the old one's edits
+            Pending::Quit | Pending::SwitchList => self.unsaved(),
+            Pending::Open(note, _) if Rc::ptr_eq(note, &self.note) => vec![],
+            _ if self.note.borrow().edited => vec![self.note.clone()],
+            _ => vec![],
+        };
+        if unsaved.is_empty() {
+            self.carry_on(then);
+        } else {
+            self.popup = Some(Popup::Unsaved {
+                then,
+                notes: unsaved,
+            });
+        }
+    }
+
+    // every note with unsaved edits, the open one included when it isn't in the list yet
+    fn unsaved(&self) -> Vec<RcRc<Note>> {
+        let mut unsaved: Vec<RcRc<Note>> = self
+            .note_list
+            .notes
+            .iter()
+            .filter(|note| note.borrow().edited)
+            .cloned()
+            .collect();
+        if self.note.borrow().edited && !unsaved.iter().any(|note| Rc::ptr_eq(note, &self.note)) {
+            unsaved.push(self.note.clone());
+        }
+        unsaved
+    }
+
+    pub fn carry_on(&mut self, then: Pending) {
+        match then {
+            Pending::Quit => self.exit = true,
+            Pending::Open(note, cursor) => self.switch_to(note, cursor),
+            Pending::New(folder) => self.start_note(folder),
+            Pending::SwitchList => self.load_list(),
+        }
+    }
+
+    fn load_list(&mut self) {
+        let list = &self.note_list;
+        let (root, local_list) = if !list.local_list {
+            let root = list.local_path.join(&list.local_dir);
+            std::fs::DirBuilder::new().create(&root).unwrap_or(());
+            (root, true)
+        } else {
+            (list.path.clone(), false)
+        };
+        match get_notes(&root, &self.settings.extension, &self.settings.templates) {
+            Ok((mut notes, skipped)) => {
+                if local_list && notes.is_empty() {
+                    notes.push(rc_rc(Note::create_note()))
+                }
+                self.note_list.local_list = local_list;
+                self.note_list.notes = notes;
+                self.note_list.index = 0;
+                self.note_list.collapsed.clear();
+                let mut skipped = skipped;
+                if let Err(err) = self.watch() {
+                    skipped.push(err);
+                }
+                self.report_all(skipped);
+            }
+            Err(err) => self.report(err),
+        }
+    }
+
+    fn start_note(&mut self, folder: PathBuf) {
+        self.note_list.is_active = false;
+        self.note = rc_rc(Note::create_note());
+        let mut note = self.note.borrow_mut();
+        note.is_active = true;
+        note.folder = folder;
+        drop(note);
+        self.backlinks = Backlinks::default();
+        self.current_frame = CurrentFrame::Note;
+        self.cursor_column = 0;
+        self.last_edit = None;
+    }
+
+    fn switch_to(&mut self, note: RcRc<Note>, cursor: (usize, usize)) {
+        self.note.borrow_mut().is_active = false;
+        self.note = note;
+        self.note.borrow_mut().is_active = true;
+        (self.cursor_row, self.cursor_column) = cursor;
+        self.note_list.is_active = false;
+        self.backlinks.is_active = false;
+        self.tag_tree.is_active = false;
+        self.agenda.is_active = false;
+        self.current_frame = CurrentFrame::Note;
+        self.last_edit = None;
+        self.refresh_backlinks();
+    }
+
+    // write a note to disk and drop its swap file, false when it couldn't be written or the
+    // file changed underneath it, which asks what to do instead
+    pub fn save(&mut self, note: &RcRc<Note>) -> bool {
+        self.store(note, false)
+    }
+
+    // save over whatever is on disk now
+    pub fn overwrite(&mut self, note: &RcRc<Note>) -> bool {
+        self.store(note, true)
+    }
+
+    fn store(&mut self, note: &RcRc<Note>, overwrite: bool) -> bool {
+        let root = self.note_list.root();
+        let extension = self.settings.extension.clone();
+        if Rc::ptr_eq(note, &self.note) {
+            self.last_edit = None;
+        }
+        if note.borrow().title.is_empty() {
+            self.report(Error::Untitled);
+            return false;
+        }
+        if !overwrite {
+            match changed_on_disk(&root, &extension, &note.borrow()) {
+                Ok(None) => {}
+                Ok(Some(disk)) => {
+                    self.popup = Some(Popup::Conflict {
+                        note: note.clone(),
+                        disk,
+                    });
+                    return false;
+                }
+                Err(err) => {
+                    self.report(err);
+                    return false;
+                }
+            }
+        }
+        let written = write_file(&root, &extension, &mut note.borrow_mut());
+        if let Err(err) = written {
+            self.report(err);
+            return false;
+        }
+        {
+            let mut note = note.borrow_mut();
+            note.edited = false;
+            note.refresh_tags_links();
+        }
+        if let Err(err) = swap::remove(&root, &extension, &note.borrow()) {
+            self.report(err);
+        }
+        self.refresh_backlinks();
+        true
+    }
+
+    // throw away a note's unsaved edits, reading it back if it was ever saved
+    pub fn discard(&mut self, note: &RcRc<Note>) {
+        let root = self.note_list.root();
+        let extension = self.settings.extension.clone();
+        if Rc::ptr_eq(note, &self.note) {
+            self.last_edit = None;
+        }
+        if let Err(err) = swap::remove(&root, &extension, &note.borrow()) {
+            self.report(err);
+        }
+        let path = note.borrow().file_path(&root, &extension);
+        if !path.exists() {
+            note.borrow_mut().edited = false;
+            return;
+        }
+        match read_file(&path).and_then(|text| parse_file(text, &path, &root)) {
+            Ok(parsed) => {
+                let parsed = parsed.borrow().clone();
+                self.reload(note, parsed);
+            }
+            Err(err) => self.report(err),
+        }
+        self.refresh_backlinks();
+    }
+
+    // merge the edits made here and the file's new contents over what was last read from it,
+    // where both sides changed the same lines the note is left holding conflict markers
+    pub fn merge(&mut self, note: &RcRc<Note>, disk: String) {
+        let root = self.note_list.root();
+        let path = note.borrow().file_path(&root, &self.settings.extension);
+        let (base, mine) = {
+            let note = note.borrow();
+            let base = note.disk.as_ref().map_or(String::new(), |disk| disk.contents.clone());
+            // the text before merging stays one undo away
+            let cursor = (self.cursor_row, self.cursor_column);
+            history::of(&mut self.history, &note).commit(&note.text, cursor);
+            (base, note.file_contents())
+        };
+        let merged = diffy::merge(&base, &mine, &disk).unwrap_or_else(|conflicted| conflicted);
+        let mut parsed = match parse_file(merged, &path, &root) {
+            Ok(parsed) => parsed.borrow().clone(),
+            Err(err) => {
+                self.report(err);
+                return;
+            }
+        };
+        // the merge already holds the file's changes, so saving it isn't another conflict
+        parsed.disk = Some(OnDisk::new(&path, disk));
+        parsed.edited = true;
+        let row = parsed.text.split('\n').position(|line| line.starts_with("<<<<<<<"));
+        self.reload(note, parsed);
+        self.open(note.clone(), (row.unwrap_or(0), 0));
+    }
+
+    // put a swap file's edits over its note, unsaved, the swap file stays until they're saved
+    pub fn recover(&mut self, swap: &PathBuf, path: &Path) -> Result<()> {
+        let root = self.note_list.root();
+        let mut parsed = parse_file(read_file(swap)?, path, &root)?.borrow().clone();
+        // the note's own file, not the swap, is what saving has to check against
+        parsed.disk = read_file(&path.to_path_buf())
+            .ok()
+            .map(|contents| OnDisk::new(path, contents));
+        let existing = self
+            .note_list
+            .notes
+            .iter()
+            .find(|note| note.borrow().id() == parsed.id())
+            .cloned();
+        match existing {
+            Some(note) => {
+                self.reload(&note, parsed);
+                note.borrow_mut().edited = true;
+            }
+            None => {
+                let mut parsed = parsed;
+                parsed.edited = true;
+                self.note_list.notes.push(rc_rc(parsed));
+            }
+        }
+        Ok(())
+    }
+
+    // show an error over the current frame, stacking with any already shown
+    pub fn report(&mut self, err: Error) {
+        self.report_all(vec![err]);
+    }
+
+    pub fn report_all(&mut self, errors: Vec<Error>) {
+        if errors.is_empty() {
+            return;
+        }
+        let mut messages = match self.popup.take() {
+            Some(Popup::Error(messages)) => messages,
+            _ => vec![],
+        };
+        messages.extend(errors.iter().map(|err| err.to_string()));
+        self.popup = Some(Popup::Error(messages));
+    }
+
+}
+
+// run the user's editor on a file, the command may carry its own arguments
+fn launch_editor(path: &Path) -> Result<()> {
+    let command = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut parts = command.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let failed = |reason: String| Error::Editor {
+        command: command.clone(),
+        reason,
+    };
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|err| failed(format!("could not be started: {}", err)))?;
+    if !status.success() {
+        return Err(failed(format!("exited with {}", status)));
+    }
+    Ok(())
 }
 
 impl Widget for &App {
@@ -163,9 +831,15 @@ impl Widget for &App {
         let note_ref = &self.note.borrow_mut().clone();
         let note_list_ref = &self.note_list;
 
-        let instructions = match self.current_frame {
-            CurrentFrame::Note => note_ref.get_instructions(),
-            CurrentFrame::List => note_list_ref.get_instructions(),
+        let instructions = match (&self.popup, &self.current_frame) {
+            (Some(popup), _) => popup.get_instructions(),
+            (None, CurrentFrame::Note) => note_ref.get_instructions(&self.settings.keymap),
+            (None, CurrentFrame::List) => note_list_ref.get_instructions(&self.settings.keymap),
+            (None, CurrentFrame::Backlinks) => {
+                self.backlinks.get_instructions(&self.settings.keymap)
+            }
+            (None, CurrentFrame::Tags) => self.tag_tree.get_instructions(&self.settings.keymap),
+            (None, CurrentFrame::Agenda) => self.agenda.get_instructions(&self.settings.keymap),
         };
 
         let pos = Title::from(vec![self.cursor_column.to_span()," ".to_span(),self.cursor_row.to_span()]);
@@ -173,7 +847,11 @@ impl Widget for &App {
             .alignment(Alignment::Center)
             .render(area, buf);
         if self.note_list.is_search {
-        Paragraph::new("search: ".to_string() + self.note_list.search.as_ref().unwrap_or(&"".to_string()))
+        let mut search = Line::raw("search: ".to_string() + self.note_list.search.as_ref().unwrap_or(&"".to_string()));
+        if let Some(err) = self.note_list.query_error() {
+            search.push_span(format!("  {}", err).fg(theme().error));
+        }
+        Paragraph::new(search)
             .alignment(Alignment::Left)
             .render(area, buf);
         }
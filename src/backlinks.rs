@@ -0,0 +1,156 @@
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::block::Title;
+use ratatui::widgets::{Block, Paragraph};
+use style::Styled;
+
+use crate::app::{App, CurrentFrame};
+use crate::list::MyList;
+use crate::note::Note;
+use crate::settings::{theme, Keymap};
+use crate::traits::ThisFrame;
+use crate::utils::RcRc;
+
+#[derive(Debug, Clone)]
+pub struct Backlink {
+    pub note: RcRc<Note>,
+    pub row: usize,
+    // the line the link sits on
+    pub context: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Backlinks {
+    pub entries: Vec<Backlink>,
+    pub index: usize,
+    pub is_active: bool,
+}
+
+impl ThisFrame for Backlinks {
+    fn get_instructions(&self, _keymap: &Keymap) -> Title<'_> {
+        Title::from(Line::from(vec![
+            " Back ".into(),
+            "<esc>".blue().bold(),
+            " Scroll Up ".into(),
+            "<UP>".blue().bold(),
+            " Scroll Down ".into(),
+            "<DOWN>".blue().bold(),
+            " Open Note ".into(),
+            "<return>".blue().bold(),
+        ]))
+    }
+
+    fn get_type(&self) -> String {
+        "Backlinks".to_string()
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => {
+                app.backlinks.is_active = false;
+                app.note.borrow_mut().is_active = true;
+                app.current_frame = CurrentFrame::Note;
+            }
+            KeyCode::Up => {
+                if self.index == 0 {
+                    app.backlinks.index = self.entries.len().saturating_sub(1);
+                } else {
+                    app.backlinks.index = self.index - 1;
+                }
+            }
+            KeyCode::Down => {
+                if self.index + 1 >= self.entries.len() {
+                    app.backlinks.index = 0;
+                } else {
+                    app.backlinks.index = self.index + 1;
+                }
+            }
+            KeyCode::Enter => {
+                let Some(backlink) = self.entries.get(self.index) else {
+                    return;
+                };
+                app.open(backlink.note.to_owned(), (backlink.row, 0));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Backlinks {
+    // every link in the vault that resolves to the note with this id
+    pub fn collect(list: &MyList, id: &Path) -> Self {
+        let mut entries = vec![];
+        let Some(target) = list.notes.iter().find(|note| note.borrow().id() == id) else {
+            return Backlinks::default();
+        };
+        // names a link could use to reach the target, checked before the full resolve
+        let names: Vec<String> = {
+            let target = target.borrow();
+            let mut names = vec![target.title.to_lowercase()];
+            names.extend(target.frontmatter.aliases.iter().map(|alias| alias.to_lowercase()));
+            names
+        };
+        for note in list.notes.iter() {
+            let source = note.borrow();
+            if source.id() == id {
+                continue;
+            }
+            for link in source.links.iter().flatten() {
+                let name = link.target.rsplit('/').next().unwrap_or("").to_lowercase();
+                if !names.contains(&name)
+                    || list.resolve(link).is_none_or(|found| found.borrow().id() != id)
+                {
+                    continue;
+                }
+                entries.push(Backlink {
+                    note: note.to_owned(),
+                    row: link.row,
+                    context: source.text.lines().nth(link.row).unwrap_or("").trim().to_string(),
+                });
+            }
+        }
+        Backlinks {
+            entries,
+            index: 0,
+            is_active: false,
+        }
+    }
+}
+
+impl Widget for &Backlinks {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let title_text = format!(" Backlinks ({}) ", self.entries.len());
+        let title = if self.is_active {
+            Title::from(title_text.fg(theme().text).bold())
+        } else {
+            Title::from(title_text.fg(theme().text).dim())
+        };
+        let mut block = Block::bordered()
+            .title(title.alignment(Alignment::Center))
+            .border_set(symbols::border::ROUNDED);
+        if self.is_active {
+            block = block.set_style(theme().active);
+        } else {
+            block = block.set_style(theme().inactive)
+        }
+        let mut lines: Vec<Line> = vec![];
+        for (count, backlink) in self.entries.iter().enumerate() {
+            let colour = if self.is_active && self.index == count {
+                theme().selected
+            } else {
+                theme().text
+            };
+            lines.push(Line::raw(backlink.note.borrow().title.clone()).style(colour).bold());
+            lines.push(Line::raw("  ".to_string() + &backlink.context).style(colour).dim());
+        }
+        Paragraph::new(lines)
+            .left_aligned()
+            .block(block)
+            .render(area, buf);
+    }
+}
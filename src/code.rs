@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+use crate::settings::theme;
+
+// the grammars and colour schemes bundled with syntect, loaded on first use
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+// a large cache gets dropped whole rather than tracking which blocks are stale
+const CACHE_LIMIT: usize = 256;
+
+thread_local! {
+    // highlighted blocks keyed by language, text and colour scheme, so typing only redoes the
+    // block being edited
+    static CACHE: RefCell<HashMap<u64, Vec<Line<'static>>>> = RefCell::new(HashMap::new());
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+pub fn has_theme(name: &str) -> bool {
+    themes().themes.contains_key(name)
+}
+
+// one line per line of the block body, None for a language without a bundled grammar
+pub fn highlight(language: &str, body: &str) -> Option<Vec<Line<'static>>> {
+    if language.is_empty() {
+        return None;
+    }
+    let scheme = theme().syntax.clone();
+    let mut hasher = DefaultHasher::new();
+    (language, body, &scheme).hash(&mut hasher);
+    let key = hasher.finish();
+    if let Some(lines) = CACHE.with(|cache| cache.borrow().get(&key).cloned()) {
+        return Some(lines);
+    }
+    let syntax = syntaxes().find_syntax_by_token(language)?;
+    let scheme = themes().themes.get(&scheme)?;
+    let mut highlighter = HighlightLines::new(syntax, scheme);
+    let mut lines = vec![];
+    for line in body.split('\n') {
+        let line = line.to_string() + "\n";
+        let pieces = highlighter.highlight_line(&line, syntaxes()).ok()?;
+        let spans: Vec<Span<'static>> = pieces
+            .into_iter()
+            .map(|(style, piece)| (style, piece.trim_end_matches('\n')))
+            .filter(|(_, piece)| !piece.is_empty())
+            .map(|(style, piece)| Span::styled(piece.to_string(), to_style(style)))
+            .collect();
+        lines.push(Line::from(spans));
+    }
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.len() >= CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(key, lines.clone());
+    });
+    Some(lines)
+}
+
+// foreground and font style only, the terminal keeps its own background
+fn to_style(style: syntect::highlighting::Style) -> Style {
+    let colour = style.foreground;
+    let mut modifier = Modifier::empty();
+    if style.font_style.contains(FontStyle::BOLD) {
+        modifier |= Modifier::BOLD;
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        modifier |= Modifier::ITALIC;
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        modifier |= Modifier::UNDERLINED;
+    }
+    Style::new()
+        .fg(Color::Rgb(colour.r, colour.g, colour.b))
+        .add_modifier(modifier)
+}
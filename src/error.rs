@@ -0,0 +1,65 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    Io { path: PathBuf, source: io::Error },
+    NotUtf8(PathBuf),
+    Config(config::ConfigError),
+    NoConfig(PathBuf),
+    InvalidSetting { key: &'static str, reason: String },
+    NoHome,
+    Editor { command: String, reason: String },
+    Untitled,
+    Watch { path: PathBuf, reason: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // wrap an io error with the path it happened on, non-UTF-8 reads get their own variant
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        if source.kind() == io::ErrorKind::InvalidData {
+            return Error::NotUtf8(path.to_path_buf());
+        }
+        Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::NotUtf8(path) => write!(f, "{}: not valid UTF-8", path.display()),
+            Error::Config(err) => write!(f, "config: {}", err),
+            Error::NoConfig(path) => write!(f, "no config found, expected {}", path.display()),
+            Error::InvalidSetting { key, reason } => write!(f, "config: `{}` {}", key, reason),
+            Error::NoHome => write!(f, "could not find the home directory"),
+            Error::Editor { command, reason } => write!(f, "editor `{}` {}", command, reason),
+            Error::Untitled => write!(f, "give the note a title before saving it"),
+            Error::Watch { path, reason } => {
+                write!(f, "{}: can't watch for changes, {}", path.display(), reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Config(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<config::ConfigError> for Error {
+    fn from(err: config::ConfigError) -> Self {
+        Error::Config(err)
+    }
+}
@@ -1,105 +1,338 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::fs;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
 use std::path::Path;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::sync::LazyLock;
+use std::time::SystemTime;
 
-use config::builder::DefaultState;
-use config::ConfigBuilder;
 use regex::Regex;
 
 use crate::app::InputMode;
+use crate::error::{Error, Result};
+use crate::frontmatter::Frontmatter;
+use crate::markdown::{fenced_rows, find_tags};
 use crate::note::Link;
 use crate::note::Note;
-use crate::note::Tag;
+use crate::note::{Priority, Tag, Task};
 use crate::utils::rc_rc;
 use crate::utils::RcRc;
 
-use config::Config;
+// a note's file as it was last read or written, kept to notice when something else changes it
+// and as the common ancestor when merging those changes
+#[derive(Debug, Clone)]
+pub struct OnDisk {
+    pub modified: Option<SystemTime>,
+    pub hash: u64,
+    pub contents: String,
+}
 
+impl OnDisk {
+    pub fn new(path: &Path, contents: String) -> Self {
+        OnDisk {
+            modified: fs::metadata(path).and_then(|meta| meta.modified()).ok(),
+            hash: hash_of(&contents),
+            contents,
+        }
+    }
+}
 
+fn hash_of(contents: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
 
-pub fn read_file(path: &PathBuf) -> String {
-    fs::read_to_string(path).unwrap()
+pub fn read_file(path: &PathBuf) -> Result<String> {
+    fs::read_to_string(path).map_err(|err| Error::io(path, err))
 }
 
-pub fn list_files(path: &str) -> Vec<PathBuf> {
-    let paths = fs::read_dir(path).unwrap();
+// walk the vault, descending into every folder that isn't hidden (.git, .obsidian, ...). a sub
+// folder that can't be read goes into `skipped` rather than hiding the rest of the vault
+pub fn list_files(path: &Path, extension: &str, skipped: &mut Vec<Error>) -> Result<Vec<PathBuf>> {
+    let paths = fs::read_dir(path).map_err(|err| Error::io(path, err))?;
     let mut files: Vec<PathBuf> = vec![];
-    for path in paths {
-        match path {
-            Ok(file) => match file.file_type() {
-                Ok(s) => {
-                    if s.is_file() {
-                        files.push(file.path())
-                    }
-                }
-                Err(_) => continue,
+    for path in paths.flatten() {
+        let hidden = path.file_name().to_string_lossy().starts_with('.');
+        match path.file_type() {
+            Ok(s) if s.is_dir() && !hidden => match list_files(&path.path(), extension, skipped) {
+                Ok(mut found) => files.append(&mut found),
+                Err(err) => skipped.push(err),
             },
-            Err(_) => todo!(),
+            Ok(s) if s.is_file() && path.path().extension().is_some_and(|ext| ext == extension) => {
+                files.push(path.path())
+            }
+            _ => continue,
         }
     }
-    files
+    Ok(files)
 }
 
-pub fn get_notes(path: &str) -> Vec<RcRc<Note>> {
-    let files = list_files(path);
+// notes that fail to load are skipped and their errors handed back alongside the rest. the
+// templates folder holds the skeletons new notes start from, which aren't notes themselves
+pub fn get_notes(
+    root: &Path,
+    extension: &str,
+    templates: &str,
+) -> Result<(Vec<RcRc<Note>>, Vec<Error>)> {
+    let mut skipped: Vec<Error> = vec![];
+    let files = list_files(root, extension, &mut skipped)?;
     let mut contents: Vec<RcRc<Note>> = vec![];
-    for file in files.iter() {
-        let parsed = parse_file(read_file(file), file);
-        contents.push(parsed);
+    for file in files.iter().filter(|file| !is_template(root, templates, file)) {
+        match read_file(file).and_then(|text| parse_file(text, file, root)) {
+            Ok(parsed) => contents.push(parsed),
+            Err(err) => skipped.push(err),
+        }
     }
-    contents
+    Ok((contents, skipped))
+}
+
+pub fn is_template(root: &Path, templates: &str, path: &Path) -> bool {
+    !templates.is_empty() && path.starts_with(root.join(templates))
 }
 
-pub fn parse_file(file_contents: String, path: &Path) -> RcRc<Note> {
-    let note_text = &file_contents;
-    let (tags, links) = get_tags_links(&file_contents);
-    let title = path.file_stem().unwrap();
-    rc_rc(Note {
-        title: title.to_str().unwrap().to_owned(),
+pub fn parse_file(file_contents: String, path: &Path, root: &Path) -> Result<RcRc<Note>> {
+    let (frontmatter, note_text) = Frontmatter::parse(&file_contents);
+    let title = path
+        .file_stem()
+        .and_then(|title| title.to_str())
+        .ok_or_else(|| Error::NotUtf8(path.to_path_buf()))?;
+    let folder = path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(root).ok())
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    let metadata = fs::metadata(path).ok();
+    let disk = OnDisk::new(path, file_contents.clone());
+    let mut note = Note {
+        key: Default::default(),
+        title: title.to_owned(),
         text: note_text.to_owned(),
-        tags: if tags.is_empty() { None } else { Some(tags) },
-        links: if links.is_empty() { None } else { Some(links) },
+        tags: None,
+        tasks: None,
+        links: None,
         mode: InputMode::Normal,
         edited: false,
         is_active: false,
         old_title: None,
-    })
+        folder,
+        frontmatter,
+        modified: metadata.as_ref().and_then(|meta| meta.modified().ok()),
+        created: metadata.as_ref().and_then(|meta| meta.created().ok()),
+        disk: Some(disk),
+        changed: false,
+        scroll: 0,
+        wrap: false,
+        highlight: None,
+    };
+    note.refresh_tags_links();
+    Ok(rc_rc(note))
 }
 
-pub fn get_tags_links(file_contents: &String) -> (Vec<Tag>, Vec<Link>) {
+pub fn get_tags_links(file_contents: &str) -> (Vec<Tag>, Vec<Link>) {
     let mut tags: Vec<Tag> = vec![];
-    let link_regex = Regex::new(r"]\((.+)\)").unwrap();
+    let links = get_links(file_contents);
+    let lines: Vec<&str> = file_contents.split('\n').collect();
+    for found in find_tags(file_contents) {
+        let tag = Tag(lines[found.row][found.start..found.end].to_owned());
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    (tags, links)
+}
+
+// compiled once, every note goes through them on load and on each reload
+static TASK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:[-*+]|\d{1,9}[.)])\s+\[([ xX])\](?:\s+(.*))?$").unwrap());
+static DUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:📅\s*|due:)(\d{4}-\d{2}-\d{2})").unwrap());
+static LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[\[([^\[\]|#]*)(?:#([^\[\]|]*))?(?:\|([^\[\]]*))?\]\]|\[([^\[\]]*)\]\(([^()\s]+)\)")
+        .unwrap()
+});
+
+// checkbox items outside code blocks, with the due date, priority and tags written on them
+pub fn get_tasks(text: &str) -> Vec<Task> {
+    let fenced = fenced_rows(text);
+    let mut tasks = vec![];
+    for (row, line) in text.split('\n').enumerate() {
+        if fenced[row] {
+            continue;
+        }
+        let Some(found) = TASK.captures(line) else {
+            continue;
+        };
+        let body = found.get(2).map_or("", |body| body.as_str());
+        let priority = [
+            ("🔺", Priority::Highest),
+            ("⏫", Priority::High),
+            ("🔼", Priority::Medium),
+            ("🔽", Priority::Low),
+            ("⏬", Priority::Lowest),
+            ("priority:highest", Priority::Highest),
+            ("priority:high", Priority::High),
+            ("priority:medium", Priority::Medium),
+            ("priority:low", Priority::Low),
+            ("priority:lowest", Priority::Lowest),
+        ]
+        .into_iter()
+        .find(|(marker, _)| body.split_whitespace().any(|word| word == *marker))
+        .map(|(_, priority)| priority);
+        let tags = find_tags(body)
+            .iter()
+            .map(|tag| Tag(body[tag.start..tag.end].to_string()))
+            .collect();
+        tasks.push(Task {
+            row,
+            done: &found[1] != " ",
+            text: body.trim().to_string(),
+            due: DUE.captures(body).map(|date| date[1].to_string()),
+            priority,
+            tags,
+        });
+    }
+    tasks
+}
+
+// [[Target#Anchor|Alias]] wikilinks and [Alias](Target.md#Anchor) markdown links
+pub fn get_links(text: &str) -> Vec<Link> {
     let mut links: Vec<Link> = vec![];
-    let t = link_regex.captures_iter(&file_contents);
-    for c in t {
-        let extract = *c.extract::<1>().1.first().unwrap();
-        links.push(Link(extract.to_owned()));
+    for (row, line) in text.lines().enumerate() {
+        for c in LINK.captures_iter(line) {
+            let whole = c.get(0).unwrap();
+            let text_of = |i: usize| c.get(i).map(|m| m.as_str().trim().to_string());
+            let (target, anchor, alias, wiki) = match c.get(1) {
+                Some(target) => (target.as_str().trim().to_string(), text_of(2), text_of(3), true),
+                None => {
+                    let url = &c[5];
+                    let (path, anchor) = match url.split_once('#') {
+                        Some((path, anchor)) => (path, Some(anchor.replace("%20", " "))),
+                        None => (url, None),
+                    };
+                    let target = path.trim_end_matches(".md").replace("%20", " ");
+                    (target, anchor, text_of(4), false)
+                }
+            };
+            links.push(Link {
+                target,
+                alias: alias.filter(|alias| !alias.is_empty()),
+                anchor: anchor.filter(|anchor| !anchor.is_empty()),
+                wiki,
+                row,
+                start: whole.start(),
+                end: whole.end(),
+            });
+        }
     }
-    for token in file_contents.split_whitespace() {
-        if token.starts_with("#") {
-            tags.push(Tag(token.to_owned()));
+    links
+}
+
+// write to a hidden file beside the note and rename it over, so a crash never leaves half a note
+pub fn write_file(root: &Path, extension: &str, note: &mut Note) -> Result<()> {
+    let file_name = note.file_path(root, extension);
+    let Some(parent) = file_name.parent() else {
+        return Err(Error::io(&file_name, io::ErrorKind::InvalidInput.into()));
+    };
+    fs::create_dir_all(parent).map_err(|err| Error::io(parent, err))?;
+    let temp = parent.join(format!(".{}.{}.tmp", note.title, extension));
+    let contents = note.file_contents();
+    let written = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, &file_name));
+    if let Err(err) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(Error::io(&file_name, err));
+    }
+    let disk = OnDisk::new(&file_name, contents);
+    note.modified = disk.modified;
+    note.disk = Some(disk);
+    note.changed = false;
+    Ok(())
+}
+
+// what the note's file holds now, when that isn't what was last read or written. a file the note
+// never came from counts too, a missing one doesn't as there's nothing to lose
+pub fn changed_on_disk(root: &Path, extension: &str, note: &Note) -> Result<Option<String>> {
+    let path = note.file_path(root, extension);
+    let modified = match fs::metadata(&path) {
+        Ok(meta) => meta.modified().ok(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(Error::io(&path, err)),
+    };
+    if let Some(disk) = &note.disk {
+        if modified.is_some() && modified == disk.modified {
+            return Ok(None);
         }
     }
-    (tags, links)
+    // a touched file with the same contents isn't a conflict
+    let contents = read_file(&path)?;
+    match &note.disk {
+        Some(disk) if disk.hash == hash_of(&contents) => Ok(None),
+        _ => Ok(Some(contents)),
+    }
 }
 
-pub fn write_file(skip_path: Option<PathBuf>,note: &mut Note) {
-    let home = std::env::home_dir().unwrap();
-    let path = home.to_str().unwrap().to_string() + "/.config/noter/config";
-    
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wikilinks_with_anchor_and_alias() {
+        let links = get_links("see [[Other Note#Plans|the plans]] and [[ Plain ]]");
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].target, "Other Note");
+        assert_eq!(links[0].anchor.as_deref(), Some("Plans"));
+        assert_eq!(links[0].alias.as_deref(), Some("the plans"));
+        assert!(links[0].wiki);
+        assert_eq!((links[0].start, links[0].end), (4, 34));
+        assert_eq!(links[1].target, "Plain");
+        assert_eq!((links[1].anchor.clone(), links[1].alias.clone()), (None, None));
+    }
 
+    #[test]
+    fn markdown_links_drop_the_extension() {
+        let links = get_links("first\n[text](folder/My%20Note.md#Some%20Heading)");
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].target, "folder/My Note");
+        assert_eq!(links[0].anchor.as_deref(), Some("Some Heading"));
+        assert_eq!(links[0].alias.as_deref(), Some("text"));
+        assert!(!links[0].wiki);
+        assert_eq!(links[0].row, 1);
+    }
 
-    let config_build = Config::builder().add_source(config::File::with_name(&path)).build().unwrap() ;
+    #[test]
+    fn empty_parts_are_none() {
+        let links = get_links("[[Note#|]] [](https://example.com)");
+        assert_eq!(links[0].target, "Note");
+        assert_eq!((links[0].anchor.clone(), links[0].alias.clone()), (None, None));
+        assert!(links[1].is_external());
+        assert_eq!(links[1].alias, None);
+    }
 
-    let mut path = config_build.try_deserialize::<HashMap<String,String>>().unwrap().get("path").unwrap().to_owned();
-    let test_location = "/mnt/g/My Drive/JamiesVault/".to_string();
-    if skip_path.is_some() {
-        path = skip_path.unwrap().to_str().unwrap().to_string();
+    #[test]
+    fn tasks_with_due_date_priority_and_tags() {
+        let text = "# list\n- [ ] pay rent 📅 2024-05-01 ⏫ #home\n  * [x] done due:2024-04-01\n1. [ ]";
+        let tasks = get_tasks(text);
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].row, 1);
+        assert!(!tasks[0].done);
+        assert_eq!(tasks[0].due.as_deref(), Some("2024-05-01"));
+        assert_eq!(tasks[0].priority, Some(Priority::High));
+        assert_eq!(tasks[0].tags, vec![Tag("#home".to_string())]);
+        assert!(tasks[1].done);
+        assert_eq!(tasks[1].due.as_deref(), Some("2024-04-01"));
+        assert_eq!(tasks[1].priority, None);
+        assert_eq!(tasks[2].text, "");
     }
-    let file_name = path + &note.title + ".md";
-    fs::write(file_name, note.clone().text.into_bytes()).unwrap();
 
+    #[test]
+    fn no_tasks_in_code_or_plain_brackets() {
+        let text = "```\n- [ ] not a task\n```\n[ ] no marker\n-[ ] no space\n- [y] bad mark";
+        assert!(get_tasks(text).is_empty());
+    }
 }
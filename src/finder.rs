@@ -0,0 +1,147 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::note::Note;
+use crate::settings::theme;
+use crate::utils::RcRc;
+
+// a fuzzy picker over every note's title and aliases
+#[derive(Debug, Clone)]
+pub struct Finder {
+    pub query: String,
+    pub index: usize,
+    pub notes: Vec<RcRc<Note>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub note: RcRc<Note>,
+    // the title or alias that matched
+    pub label: String,
+    pub alias: bool,
+    pub score: i64,
+    // char indices of label that matched the query
+    pub positions: Vec<usize>,
+}
+
+impl Finder {
+    pub fn new(notes: Vec<RcRc<Note>>) -> Self {
+        Finder {
+            query: String::new(),
+            index: 0,
+            notes,
+        }
+    }
+
+    // each note once, through whichever of its names matches best
+    pub fn hits(&self) -> Vec<Hit> {
+        let matcher = SkimMatcherV2::default();
+        let mut hits: Vec<Hit> = self
+            .notes
+            .iter()
+            .filter_map(|note| {
+                let borrowed = note.borrow();
+                let names = std::iter::once((&borrowed.title, false))
+                    .chain(borrowed.frontmatter.aliases.iter().map(|alias| (alias, true)));
+                names
+                    .filter_map(|(name, alias)| {
+                        let (score, positions) = if self.query.is_empty() {
+                            (0, vec![])
+                        } else {
+                            matcher.fuzzy_indices(name, &self.query)?
+                        };
+                        Some(Hit {
+                            note: note.clone(),
+                            label: name.clone(),
+                            alias,
+                            score,
+                            positions,
+                        })
+                    })
+                    .max_by_key(|hit| (hit.score, !hit.alias))
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.label.len().cmp(&b.label.len()))
+                .then_with(|| a.label.cmp(&b.label))
+        });
+        hits
+    }
+
+    pub fn push(&self, c: char) -> Self {
+        Finder {
+            query: self.query.clone() + &c.to_string(),
+            index: 0,
+            notes: self.notes.clone(),
+        }
+    }
+
+    pub fn pop(&self) -> Self {
+        let mut graphemes: Vec<&str> = self.query.graphemes(true).collect();
+        graphemes.pop();
+        Finder {
+            query: graphemes.concat(),
+            index: 0,
+            notes: self.notes.clone(),
+        }
+    }
+
+    pub fn step(&self, down: bool) -> Self {
+        let count = self.hits().len();
+        let index = match (down, self.index) {
+            (_, _) if count == 0 => 0,
+            (true, index) if index + 1 >= count => 0,
+            (true, index) => index + 1,
+            (false, 0) => count - 1,
+            (false, index) => index - 1,
+        };
+        Finder {
+            index,
+            ..self.clone()
+        }
+    }
+}
+
+// the prompt and as many hits as fit in `height` rows, keeping the selection in view
+pub fn finder_lines(finder: &Finder, height: usize) -> Vec<Line<'static>> {
+    let hits = finder.hits();
+    let mut lines = vec![
+        Line::from(vec![
+            "> ".into(),
+            finder.query.clone().fg(theme().text),
+            "█".into(),
+        ]),
+        Line::from(format!("  {}/{}", hits.len(), finder.notes.len()).dim()),
+    ];
+    let visible = height.saturating_sub(lines.len()).max(1);
+    let start = (finder.index + 1).saturating_sub(visible);
+    for (count, hit) in hits.iter().enumerate().skip(start).take(visible) {
+        let colour = if count == finder.index {
+            theme().selected
+        } else {
+            theme().text
+        };
+        let mut spans: Vec<Span> = vec![if count == finder.index {
+            "▶ ".fg(colour)
+        } else {
+            "  ".into()
+        }];
+        for (index, c) in hit.label.chars().enumerate() {
+            let span = c.to_string().fg(colour);
+            spans.push(if hit.positions.contains(&index) {
+                span.fg(theme().highlight).bold()
+            } else {
+                span
+            });
+        }
+        if hit.alias {
+            spans.push(format!("  → {}", hit.note.borrow().title).dim());
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
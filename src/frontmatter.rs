@@ -0,0 +1,285 @@
+use yaml_rust2::{yaml::Hash, Yaml, YamlEmitter, YamlLoader};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Frontmatter {
+    pub tags: Vec<String>,
+    pub aliases: Vec<String>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    // every other key, in the order it appeared in the file
+    pub extra: Hash,
+    // the top level blocks exactly as they were read, so untouched keys are
+    // written back byte-for-byte
+    source: Vec<Block>,
+    open: String,
+    close: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Block {
+    key: String,
+    raw: String,
+    // None for keys that aren't strings, like `2024: year`, which can't be edited and are
+    // always written back as they were
+    value: Option<Yaml>,
+}
+
+impl Frontmatter {
+    // split a file into its frontmatter and the body that follows it
+    pub fn parse(file_contents: &str) -> (Frontmatter, &str) {
+        let Some(open_len) = ["---\n", "---\r\n"]
+            .iter()
+            .find(|fence| file_contents.starts_with(*fence))
+            .map(|fence| fence.len())
+        else {
+            return (Frontmatter::default(), file_contents);
+        };
+        let mut yaml_end = open_len;
+        let mut close = None;
+        for line in file_contents[open_len..].split_inclusive('\n') {
+            if matches!(line.trim_end(), "---" | "...") {
+                close = Some(line);
+                break;
+            }
+            yaml_end += line.len();
+        }
+        let Some(close) = close else {
+            return (Frontmatter::default(), file_contents);
+        };
+        let yaml = &file_contents[open_len..yaml_end];
+        let body = &file_contents[yaml_end + close.len()..];
+        let hash = match YamlLoader::load_from_str(yaml) {
+            Ok(docs) => match docs.into_iter().next() {
+                Some(Yaml::Hash(hash)) => hash,
+                None => Hash::new(),
+                // not a mapping, leave the whole file as body text
+                Some(_) => return (Frontmatter::default(), file_contents),
+            },
+            Err(_) => return (Frontmatter::default(), file_contents),
+        };
+
+        let mut frontmatter = Frontmatter {
+            open: file_contents[..open_len].to_string(),
+            close: close.to_string(),
+            ..Default::default()
+        };
+        for (key, raw) in split_blocks(yaml) {
+            let value = hash.get(&Yaml::String(key.clone())).cloned();
+            frontmatter.source.push(Block { key, raw, value });
+        }
+        for (key, value) in hash {
+            let Some(key) = key.as_str().map(str::to_owned) else {
+                continue;
+            };
+            match key.as_str() {
+                "tags" | "tag" => frontmatter.tags = to_list(&value),
+                "aliases" | "alias" => frontmatter.aliases = to_list(&value),
+                "created" => frontmatter.created = to_scalar(&value),
+                "updated" => frontmatter.updated = to_scalar(&value),
+                _ => {
+                    frontmatter.extra.insert(Yaml::String(key), value);
+                }
+            }
+        }
+        (frontmatter, body)
+    }
+
+    // true when there is nothing to write, files without frontmatter stay without it
+    pub fn is_empty(&self) -> bool {
+        self.open.is_empty() && self.typed().is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Yaml> {
+        self.extra.get(&Yaml::String(key.to_string()))
+    }
+
+    pub fn set(&mut self, key: &str, value: Yaml) {
+        self.extra.insert(Yaml::String(key.to_string()), value);
+    }
+
+    // the current value of every key, typed fields first
+    fn typed(&self) -> Vec<(String, Yaml)> {
+        let list = |items: &Vec<String>| {
+            Yaml::Array(items.iter().map(|item| Yaml::String(item.clone())).collect())
+        };
+        let mut typed = vec![];
+        if !self.tags.is_empty() {
+            typed.push(("tags".to_string(), list(&self.tags)));
+        }
+        if !self.aliases.is_empty() {
+            typed.push(("aliases".to_string(), list(&self.aliases)));
+        }
+        if let Some(created) = &self.created {
+            typed.push(("created".to_string(), Yaml::String(created.clone())));
+        }
+        if let Some(updated) = &self.updated {
+            typed.push(("updated".to_string(), Yaml::String(updated.clone())));
+        }
+        for (key, value) in self.extra.iter() {
+            if let Some(key) = key.as_str() {
+                typed.push((key.to_string(), value.clone()));
+            }
+        }
+        typed
+    }
+
+    // whether a key read from the file still holds the value it was read with
+    fn unchanged(&self, block: &Block) -> bool {
+        let Some(value) = &block.value else {
+            return true;
+        };
+        match block.key.as_str() {
+            // comments and blank lines ahead of the first key
+            "" => true,
+            "tags" | "tag" => to_list(value) == self.tags,
+            "aliases" | "alias" => to_list(value) == self.aliases,
+            "created" => to_scalar(value) == self.created,
+            "updated" => to_scalar(value) == self.updated,
+            key => self.get(key) == Some(value),
+        }
+    }
+}
+
+impl std::fmt::Display for Frontmatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let typed = self.typed();
+        let mut written: Vec<&str> = vec![];
+        write!(f, "{}", if self.open.is_empty() { "---\n" } else { &self.open })?;
+        for block in self.source.iter() {
+            let canonical = match block.key.as_str() {
+                "tag" => "tags",
+                "alias" => "aliases",
+                key => key,
+            };
+            written.push(canonical);
+            if self.unchanged(block) {
+                write!(f, "{}", block.raw)?;
+            } else if let Some((_, value)) = typed.iter().find(|(key, _)| key == canonical) {
+                write!(f, "{}", emit(&block.key, value))?;
+            }
+        }
+        for (key, value) in typed.iter() {
+            if !written.contains(&key.as_str()) {
+                write!(f, "{}", emit(key, value))?;
+            }
+        }
+        write!(f, "{}", if self.close.is_empty() { "---\n" } else { &self.close })
+    }
+}
+
+// break the yaml source into top level keys, each carrying its nested lines
+fn split_blocks(yaml: &str) -> Vec<(String, String)> {
+    let mut blocks: Vec<(String, String)> = vec![];
+    for line in yaml.split_inclusive('\n') {
+        let top_level = !line.starts_with([' ', '\t', '-', '#', '\r', '\n']);
+        match line.split_once(':') {
+            Some((key, _)) if top_level => {
+                let key = key.trim().trim_matches(['"', '\'']).to_string();
+                blocks.push((key, line.to_string()));
+            }
+            _ => match blocks.last_mut() {
+                Some((_, raw)) => raw.push_str(line),
+                None => blocks.push((String::new(), line.to_string())),
+            },
+        }
+    }
+    blocks
+}
+
+fn emit(key: &str, value: &Yaml) -> String {
+    let mut hash = Hash::new();
+    hash.insert(Yaml::String(key.to_string()), value.clone());
+    let mut out = String::new();
+    if YamlEmitter::new(&mut out).dump(&Yaml::Hash(hash)).is_err() {
+        return String::new();
+    }
+    out.trim_start_matches("---").trim_start_matches('\n').to_string() + "\n"
+}
+
+fn to_list(value: &Yaml) -> Vec<String> {
+    match value {
+        Yaml::Array(items) => items.iter().filter_map(to_scalar).collect(),
+        Yaml::String(items) => items
+            .split([',', ' '])
+            .filter(|item| !item.is_empty())
+            .map(str::to_owned)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn to_scalar(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(file: &str) -> String {
+        let (frontmatter, body) = Frontmatter::parse(file);
+        frontmatter.to_string() + body
+    }
+
+    #[test]
+    fn keeps_comments_and_layout() {
+        let file = "---\n# written by hand\ntitle:   Spaced  \ntags: [a, b]\n\n# trailing\n---\nbody\n";
+        assert_eq!(round_trip(file), file);
+    }
+
+    #[test]
+    fn keeps_crlf() {
+        let file = "---\r\ntags:\r\n  - a\r\n  - b\r\nrating: 3\r\n---\r\nbody\r\n";
+        assert_eq!(round_trip(file), file);
+        let (frontmatter, body) = Frontmatter::parse(file);
+        assert_eq!(frontmatter.tags, vec!["a", "b"]);
+        assert_eq!(body, "body\r\n");
+    }
+
+    #[test]
+    fn keeps_empty_frontmatter() {
+        let file = "---\n---\nbody\n";
+        assert_eq!(round_trip(file), file);
+    }
+
+    #[test]
+    fn no_frontmatter_stays_none() {
+        let file = "just text\n---\nmore\n";
+        let (frontmatter, body) = Frontmatter::parse(file);
+        assert!(frontmatter.is_empty());
+        assert_eq!(body, file);
+        assert_eq!(round_trip(file), file);
+    }
+
+    #[test]
+    fn keeps_non_string_keys() {
+        let file = "---\n2024: year\ntrue: yes\ntags: [a]\n---\nbody\n";
+        assert_eq!(round_trip(file), file);
+        let (mut frontmatter, body) = Frontmatter::parse(file);
+        frontmatter.tags.push("b".to_string());
+        let saved = frontmatter.to_string() + body;
+        assert!(saved.contains("2024: year\n"));
+        assert!(saved.contains("true: yes\n"));
+        assert!(saved.contains("- b"));
+    }
+
+    #[test]
+    fn rewrites_only_changed_keys() {
+        let file = "---\ntitle: 'kept'  # note\ncreated: 2024-01-01\n---\n";
+        let (mut frontmatter, _) = Frontmatter::parse(file);
+        frontmatter.created = Some("2024-02-02".to_string());
+        frontmatter.set("new", Yaml::Integer(1));
+        let saved = frontmatter.to_string();
+        assert!(saved.starts_with("---\ntitle: 'kept'  # note\ncreated: "));
+        assert!(saved.contains("2024-02-02"));
+        assert!(saved.ends_with("new: 1\n---\n"));
+    }
+}
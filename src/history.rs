@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use crate::note::{Note, NoteKey};
+
+// an undo tree of note text snapshots, undo walks to the parent and redo to the
+// child visited last, so undoing then editing keeps the old branch around
+#[derive(Debug, Clone)]
+struct Revision {
+    text: String,
+    parent: Option<usize>,
+    // the child redo should follow
+    last_child: Option<usize>,
+    // cursor before and after the edit that led to this revision
+    before: (usize, usize),
+    after: (usize, usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    revisions: Vec<Revision>,
+    current: usize,
+    // where the cursor was when the open insert session began
+    pending: Option<(usize, usize)>,
+}
+
+impl History {
+    pub fn new(text: &str) -> Self {
+        History {
+            revisions: vec![Revision {
+                text: text.to_string(),
+                parent: None,
+                last_child: None,
+                before: (0, 0),
+                after: (0, 0),
+            }],
+            current: 0,
+            pending: None,
+        }
+    }
+
+    // an insert session is one undo step, remember where it started
+    pub fn begin(&mut self, cursor: (usize, usize)) {
+        self.pending.get_or_insert(cursor);
+    }
+
+    // record the text as a new revision if it changed since the current one
+    pub fn commit(&mut self, text: &str, cursor: (usize, usize)) {
+        let before = self.pending.take().unwrap_or(cursor);
+        if self.revisions[self.current].text == text {
+            return;
+        }
+        self.revisions.push(Revision {
+            text: text.to_string(),
+            parent: Some(self.current),
+            last_child: None,
+            before,
+            after: cursor,
+        });
+        let id = self.revisions.len() - 1;
+        self.revisions[self.current].last_child = Some(id);
+        self.current = id;
+    }
+
+    // the text and cursor to restore, or None at the root
+    pub fn undo(&mut self, text: &str, cursor: (usize, usize)) -> Option<(String, (usize, usize))> {
+        // changes made outside the editor, like a rename rewriting links, become their own step
+        self.commit(text, cursor);
+        let revision = &self.revisions[self.current];
+        let parent = revision.parent?;
+        let before = revision.before;
+        self.revisions[parent].last_child = Some(self.current);
+        self.current = parent;
+        Some((self.revisions[parent].text.clone(), before))
+    }
+
+    pub fn redo(&mut self, text: &str, cursor: (usize, usize)) -> Option<(String, (usize, usize))> {
+        self.commit(text, cursor);
+        let child = self.revisions[self.current].last_child?;
+        self.current = child;
+        let revision = &self.revisions[child];
+        Some((revision.text.clone(), revision.after))
+    }
+}
+
+// histories live on the app keyed by note, so they outlast switching notes, reloads and renames
+pub fn of<'a>(histories: &'a mut HashMap<NoteKey, History>, note: &Note) -> &'a mut History {
+    histories
+        .entry(note.key)
+        .or_insert_with(|| History::new(&note.text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untitled_notes_keep_their_own_history() {
+        let mut histories = HashMap::new();
+        let first = Note {
+            text: "first".to_string(),
+            ..Note::create_note()
+        };
+        let second = Note {
+            text: "second".to_string(),
+            ..Note::create_note()
+        };
+        of(&mut histories, &first).commit("first edited", (0, 12));
+        assert_eq!(of(&mut histories, &second).undo("second", (0, 0)), None);
+        let undone = of(&mut histories, &first).undo("first edited", (0, 12));
+        assert_eq!(undone.map(|(text, _)| text).as_deref(), Some("first"));
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+
+use crate::error::Result;
+use crate::file_reader::read_file;
+use crate::note::Note;
+use crate::settings::Journal;
+use crate::templates;
+use crate::utils::RcRc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Period {
+    pub fn format(self, journal: &Journal) -> &str {
+        match self {
+            Period::Daily => &journal.daily,
+            Period::Weekly => &journal.weekly,
+            Period::Monthly => &journal.monthly,
+        }
+    }
+
+    pub fn template(self, journal: &Journal) -> Option<&PathBuf> {
+        match self {
+            Period::Daily => journal.daily_template.as_ref(),
+            Period::Weekly => journal.weekly_template.as_ref(),
+            Period::Monthly => journal.monthly_template.as_ref(),
+        }
+    }
+
+    // the first day of the week (monday) or month holding the date
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Daily => date,
+            Period::Weekly => date - Days::new(date.weekday().num_days_from_monday() as u64),
+            Period::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    pub fn step(self, date: NaiveDate, forward: bool) -> NaiveDate {
+        let date = self.start(date);
+        let stepped = match (self, forward) {
+            (Period::Daily, true) => date.checked_add_days(Days::new(1)),
+            (Period::Daily, false) => date.checked_sub_days(Days::new(1)),
+            (Period::Weekly, true) => date.checked_add_days(Days::new(7)),
+            (Period::Weekly, false) => date.checked_sub_days(Days::new(7)),
+            (Period::Monthly, true) => date.checked_add_months(Months::new(1)),
+            (Period::Monthly, false) => date.checked_sub_months(Months::new(1)),
+        };
+        stepped.unwrap_or(date)
+    }
+
+    pub fn title(self, date: NaiveDate, journal: &Journal) -> String {
+        self.start(date).format(self.format(journal)).to_string()
+    }
+
+    // the date a journal title stands for, weeks and months read back as their first day
+    pub fn parse(self, title: &str, journal: &Journal) -> Option<NaiveDate> {
+        let format = self.format(journal);
+        let date = match self {
+            Period::Daily => NaiveDate::parse_from_str(title, format),
+            Period::Weekly => {
+                NaiveDate::parse_from_str(&format!("{}|1", title), &format!("{}|%u", format))
+            }
+            Period::Monthly => {
+                NaiveDate::parse_from_str(&format!("{}|1", title), &format!("{}|%d", format))
+            }
+        };
+        date.ok()
+    }
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+// the period and date of a note in the journal folder
+pub fn period_of(note: &Note, journal: &Journal) -> Option<(Period, NaiveDate)> {
+    if note.folder != Path::new(&journal.folder) {
+        return None;
+    }
+    [Period::Daily, Period::Weekly, Period::Monthly]
+        .into_iter()
+        .find_map(|period| Some((period, period.parse(&note.title, journal)?)))
+}
+
+// the closest existing journal note of the period before or after the date
+pub fn neighbour(
+    notes: &[RcRc<Note>],
+    journal: &Journal,
+    period: Period,
+    date: NaiveDate,
+    forward: bool,
+) -> Option<NaiveDate> {
+    let dates = notes.iter().filter_map(|note| {
+        let note = note.borrow();
+        match period_of(&note, journal) {
+            Some((found, day)) if found == period => Some(day),
+            _ => None,
+        }
+    });
+    if forward {
+        dates.filter(|day| *day > date).min()
+    } else {
+        dates.filter(|day| *day < date).max()
+    }
+}
+
+// write a new journal note from its template, dated the start of its period
+pub fn create(
+    root: &Path,
+    extension: &str,
+    journal: &Journal,
+    period: Period,
+    date: NaiveDate,
+) -> Result<(RcRc<Note>, (usize, usize))> {
+    let template = match period.template(journal) {
+        Some(template) => read_file(&root.join(template))?,
+        None => String::new(),
+    };
+    let when = period.start(date).and_time(Local::now().time());
+    let title = period.title(date, journal);
+    let folder = Path::new(&journal.folder);
+    templates::create(root, extension, folder, &title, &template, when, &HashMap::new())
+}
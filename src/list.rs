@@ -1,20 +1,26 @@
-use config::Config;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::prelude::*;
-use ratatui::widgets::block::Position;
 use ratatui::widgets::{Block, Paragraph};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashSet};
 use std::fmt::Display;
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
 use style::Styled;
 
 use ratatui::widgets::block::Title;
 
 use crate::app::{App, CurrentFrame};
-use crate::file_reader::{get_notes, get_tags_links};
-use crate::note::Tag;
-use crate::utils::{rc_rc, RcRc};
+use crate::agenda::Agenda;
+use crate::journal::{self, Period};
+use crate::popup::Popup;
+use crate::templates;
+use crate::error::{Error, Result};
+use crate::file_reader::get_notes;
+use crate::note::Link;
+use crate::settings::{theme, Keymap, Settings, Sort};
+use crate::tags::TagTree;
+use crate::query;
+use crate::search::{find_matches, matcher, search};
+use crate::utils::{byte_index, column_of, grapheme_len, render_scrollbar, RcRc};
 use crate::{note::Note, traits::ThisFrame};
 
 #[derive(Debug, Default, Clone)]
@@ -25,9 +31,30 @@ pub struct MyList {
     pub is_active: bool,
     pub is_search: bool,
     pub search: Option<String>,
+    // searching titles and bodies for the text rather than tags
+    pub full_text: bool,
     pub tag_all: bool,
     pub local_list: bool,
     pub local_path: PathBuf,
+    pub local_dir: String,
+    pub sort: Sort,
+    pub collapsed: HashSet<PathBuf>,
+    // first row on screen
+    pub scroll: usize,
+}
+
+// a row of the note tree, folders are relative to the vault root
+#[derive(Debug, Clone)]
+pub enum ListEntry {
+    Folder {
+        path: PathBuf,
+        depth: usize,
+        collapsed: bool,
+    },
+    Note {
+        note: RcRc<Note>,
+        depth: usize,
+    },
 }
 
 impl Display for MyList {
@@ -37,51 +64,34 @@ impl Display for MyList {
 }
 
 impl ThisFrame for MyList {
-    fn new() -> Self {
-        let home = std::env::home_dir().unwrap();
-        let path = home.to_str().unwrap().to_string() + "/.config/noter/config";
-
-        let config_build = Config::builder()
-            .add_source(config::File::with_name(&path))
-            .build()
-            .unwrap();
-
-        let path = config_build
-            .try_deserialize::<HashMap<String, String>>()
-            .unwrap()
-            .get("path")
-            .unwrap()
-            .to_owned();
-        let found_notes = get_notes(path.as_str());
-        let cwd = std::env::current_dir().unwrap();
-        MyList {
-            notes: found_notes,
-            index: 0,
-            path: PathBuf::from_str(path.as_str()).unwrap(),
-            is_active: true,
-            is_search: false,
-            search: None,
-            tag_all: false,
-            local_list: false,
-            local_path: cwd,
-        }
-    }
-    fn get_instructions(&self) -> ratatui::widgets::block::Title {
+    fn get_instructions(&self, keymap: &Keymap) -> ratatui::widgets::block::Title<'_> {
         Title::from(text::Line::from(vec![
             " Quit ".into(),
-            "<q>".bold().red(),
-            " Search tags ".into(),
-            "<s>".bold().blue(),
+            format!("<{}>", keymap.quit).bold().red(),
+            " Search tags/aliases ".into(),
+            format!("<{}>", keymap.search).bold().blue(),
+            " Search text ".into(),
+            format!("<{}>", keymap.text_search).bold().blue(),
+            " Find ".into(),
+            format!("<C-{}>", keymap.finder).bold().blue(),
+            " Tags ".into(),
+            format!("<{}>", keymap.tag_tree).bold().blue(),
+            " Agenda ".into(),
+            format!("<{}>", keymap.agenda).bold().blue(),
+            " Today ".into(),
+            format!("<{}/{}/{}>", keymap.daily, keymap.weekly, keymap.monthly).bold().blue(),
             " Scroll Up ".into(),
             "<UP>".bold().blue(),
             " Scroll Down".into(),
             "<DOWN>".bold().blue(),
             " Select Note ".into(),
             "<ENTER>".bold().blue(),
+            " Fold ".into(),
+            "<LEFT/RIGHT>".bold().blue(),
             " Toggle Dir ".into(),
             "<TAB>".bold().blue(),
             " New Note ".into(),
-            "<n>".bold().blue(),
+            format!("<{}>", keymap.new_note).bold().blue(),
         ]))
     }
 
@@ -89,54 +99,69 @@ impl ThisFrame for MyList {
         "List".to_string()
     }
 
+    // a key that can't move the cursor stops at its own arm rather than falling through
+    #[allow(clippy::collapsible_match)]
     fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) {
-        // let list = &app.note_list;
+        let keymap = app.settings.keymap.clone();
         match (key_event.code, self.is_search) {
-            (KeyCode::Char('q'), false) => app.exit(),
-            (KeyCode::Char('n'), false) => {
-                app.note_list.is_active = false;
-                app.note = rc_rc(Note::create_note());
-                let mut note = app.note.borrow_mut();
-                note.is_active = true;
-                app.current_frame = CurrentFrame::Note;
-                app.cursor_column = 0;
-            }
-            (KeyCode::Tab, false) => {
-                if !self.local_list {
-                    let builder = std::fs::DirBuilder::new();
-                    app.note_list.local_list = true;
-                    let path = PathBuf::from_str(&(self.local_path.to_string_lossy() + "/notes"));
-                    builder.create(path.clone().unwrap()).unwrap_or(());
-                    let mut notes = get_notes(path.unwrap_or(self.path.clone()).to_str().unwrap());
-                    if notes.is_empty() {
-                        notes.push(rc_rc(Note::create_note()))
-                    }
-                    app.note_list.notes = notes;
-                    app.note_list.index = 0
+            (KeyCode::Char(c), false) if c == keymap.quit => app.quit(),
+            (KeyCode::Char(c), false) if c == keymap.new_note => {
+                let folder = self.selected_folder();
+                let settings = &app.settings;
+                let templates = templates::list(&self.root(), &settings.templates, &settings.extension);
+                if templates.is_empty() {
+                    app.new_note(folder);
                 } else {
-                    app.note_list.local_list = false;
-                    app.note_list.notes = get_notes(self.path.to_str().unwrap());
-                    app.note_list.index = 0
+                    app.popup = Some(Popup::Templates {
+                        templates,
+                        index: 0,
+                        folder,
+                    });
                 }
             }
+            (KeyCode::Tab, false) => app.switch_list(),
             (KeyCode::Backspace, true) => {
                 if app.cursor_column == 0 {
-                } else {
-                    let search = &app.note_list.search.to_owned().unwrap();
-                    let (first, second) = search.split_at(app.cursor_column);
-                    app.cursor_column = app.cursor_column.saturating_sub(1);
-                    app.note_list
-                        .search
-                        .replace(first.split_at(app.cursor_column).0.to_string() + second);
+                } else if let Some(search) = app.note_list.search.as_mut() {
+                    app.cursor_column = app.cursor_column.min(grapheme_len(search)).saturating_sub(1);
+                    let start = byte_index(search, app.cursor_column);
+                    let end = byte_index(search, app.cursor_column + 1);
+                    search.replace_range(start..end, "");
                 }
             }
             (KeyCode::Up, false) => {
                 if app.note_list.index == 0 {
-                    app.note_list.index = self.notes.len() - 1;
+                    app.note_list.index = self.entries().len().saturating_sub(1);
                 } else {
                     app.note_list.index = app.note_list.index.saturating_sub(1);
                 }
             }
+            (KeyCode::Left, false) => match self.entries().get(self.index) {
+                Some(ListEntry::Folder { path, .. }) => {
+                    app.note_list.collapsed.insert(path.to_owned());
+                }
+                Some(ListEntry::Note { note, .. }) => {
+                    // fold the folder this note sits in and move onto it
+                    let folder = note.borrow().folder.clone();
+                    if !folder.as_os_str().is_empty() {
+                        app.note_list.collapsed.insert(folder.clone());
+                        app.note_list.index = app
+                            .note_list
+                            .entries()
+                            .iter()
+                            .position(|entry| {
+                                matches!(entry, ListEntry::Folder { path, .. } if *path == folder)
+                            })
+                            .unwrap_or(0);
+                    }
+                }
+                None => {}
+            },
+            (KeyCode::Right, false) => {
+                if let Some(ListEntry::Folder { path, .. }) = self.entries().get(self.index) {
+                    app.note_list.collapsed.remove(path);
+                }
+            }
             (KeyCode::Left, true) => {
                 if app.cursor_column > 0 {
                     app.cursor_column = app.cursor_column.saturating_sub(1);
@@ -147,90 +172,127 @@ impl ThisFrame for MyList {
                     .note_list
                     .search
                     .as_ref()
-                    .is_some_and(|search| search.len() > app.cursor_column)
+                    .is_some_and(|search| grapheme_len(search) > app.cursor_column)
                 {
                     app.cursor_column = app.cursor_column.saturating_add(1);
                 }
             }
-            (KeyCode::Char('s'), false) => {
+            (KeyCode::Char(c), false) if c == keymap.search => {
+                app.note_list.is_search = true;
+                app.note_list.full_text = false;
+                app.highlight = None;
+                app.cursor_column = 0;
+                app.note_list.index = 0;
+            }
+            (KeyCode::Char(c), false) if c == keymap.tag_tree => {
+                app.tag_tree = TagTree::new(&self.notes);
+                app.note_list.is_active = false;
+                app.current_frame = CurrentFrame::Tags;
+            }
+            (KeyCode::Char(c), false) if c == keymap.agenda => {
+                app.agenda = Agenda::collect(&self.notes);
+                app.note_list.is_active = false;
+                app.current_frame = CurrentFrame::Agenda;
+            }
+            (KeyCode::Char(c), false)
+                if c == keymap.daily || c == keymap.weekly || c == keymap.monthly =>
+            {
+                let period = match c {
+                    _ if c == keymap.daily => Period::Daily,
+                    _ if c == keymap.weekly => Period::Weekly,
+                    _ => Period::Monthly,
+                };
+                app.open_journal(period, journal::today());
+            }
+            (KeyCode::Char(c), false) if c == keymap.text_search => {
                 app.note_list.is_search = true;
+                app.note_list.full_text = true;
+                app.note_list.search = None;
+                app.highlight = None;
                 app.cursor_column = 0;
                 app.note_list.index = 0;
             }
             (KeyCode::Esc, true) => {
                 app.note_list.is_search = false;
+                app.note_list.full_text = false;
+                app.highlight = None;
             }
             (KeyCode::Char(c), true) => {
-                if app.note_list.search.is_none() {
-                    let search = &mut app.note_list.search;
-                    search.replace(c.to_string());
-                    app.cursor_column = app.cursor_column.saturating_add(1);
-                } else {
-                    let search = app.note_list.search.clone();
-                    let (first, second) = search
-                        .as_ref()
-                        .unwrap()
-                        .split_at(app.cursor_column)
-                        .to_owned();
-                    app.note_list
-                        .search
-                        .replace(first.to_owned().to_string() + &c.to_string() + second);
-                    app.cursor_column = app.cursor_column.saturating_add(1);
-                }
+                let search = app.note_list.search.get_or_insert_with(String::new);
+                let index = byte_index(search, app.cursor_column);
+                search.insert(index, c);
+                app.cursor_column = column_of(search, index + c.len_utf8());
                 app.note_list.index = 0;
             }
             (KeyCode::Down, false) => {
-                if app.note_list.index == self.notes.len() - 1 {
+                if app.note_list.index + 1 >= self.entries().len() {
                     app.note_list.index = 0;
                 } else {
                     app.note_list.index = app.note_list.index.saturating_add(1);
                 }
             }
+            (KeyCode::PageUp, _) => {
+                let page = (app.list_height / self.entry_height()).max(1);
+                app.note_list.scroll = self.scroll.saturating_sub(page * self.entry_height());
+                app.note_list.index = self.index.saturating_sub(page);
+            }
+            (KeyCode::PageDown, _) => {
+                let page = (app.list_height / self.entry_height()).max(1);
+                let last = self.rows().saturating_sub(1);
+                let rows = self.rows() * self.entry_height();
+                app.note_list.scroll = (self.scroll + page * self.entry_height())
+                    .min(rows.saturating_sub(app.list_height));
+                app.note_list.index = (self.index + page).min(last);
+            }
             (KeyCode::Up, true) => {
                 if app.note_list.index == 0 {
-                    app.note_list.index = self.filter_list(self.search.clone()).unwrap().len() - 1;
+                    app.note_list.index = self.rows().saturating_sub(1);
                 } else {
                     app.note_list.index = app.note_list.index.saturating_sub(1);
                 }
             }
             (KeyCode::Down, true) => {
-                if self
-                    .filter_list(self.search.clone())
-                    .is_some_and(|notes| notes.len() == app.note_list.index + 1)
-                {
+                if self.rows() <= app.note_list.index + 1 {
                     app.note_list.index = 0;
                 } else {
                     app.note_list.index = app.note_list.index.saturating_add(1);
                 }
             }
-            (KeyCode::Enter, false) => {
-                app.note = app
-                    .note_list
-                    .notes
-                    .get(app.note_list.index)
-                    .unwrap()
-                    .to_owned();
-                app.note_list.is_active = false;
-                app.note.borrow_mut().is_active = true;
-                app.current_frame = CurrentFrame::Note;
-                app.cursor_column = 0;
-            }
+            (KeyCode::Enter, false) => match self.entries().get(self.index) {
+                Some(ListEntry::Folder { path, collapsed, .. }) => {
+                    if *collapsed {
+                        app.note_list.collapsed.remove(path);
+                    } else {
+                        app.note_list.collapsed.insert(path.to_owned());
+                    }
+                }
+                Some(ListEntry::Note { note, .. }) => app.open(note.to_owned(), (0, 0)),
+                None => {}
+            },
             (KeyCode::Tab, true) => {
                 app.note_list.tag_all = !self.tag_all;
                 app.note_list.index = 0;
             }
             (KeyCode::Enter, true) => {
-                app.note = app
-                    .note_list
-                    .filter_list(self.search.clone())
-                    .unwrap()
-                    .get(app.note_list.index)
-                    .unwrap()
-                    .to_owned();
-                app.note_list.is_active = false;
-                app.note.borrow_mut().is_active = true;
-                app.current_frame = CurrentFrame::Note;
-                app.cursor_column = 0;
+                let Some(selected) = self.results().get(self.index).cloned() else {
+                    return;
+                };
+                // land on the first hit, n and N carry on from there
+                let mut cursor = (0, 0);
+                if self.full_text {
+                    app.highlight = self.search.clone();
+                    let note = selected.borrow();
+                    let first = self
+                        .search
+                        .as_deref()
+                        .and_then(matcher)
+                        .and_then(|regex| find_matches(&note.text, &regex).first().copied());
+                    if let Some(found) = first {
+                        let line = note.text.split('\n').nth(found.row).unwrap_or("");
+                        cursor = (found.row, column_of(line, found.start));
+                    }
+                }
+                app.open(selected, cursor);
             }
             _ => {}
         }
@@ -238,7 +300,7 @@ impl ThisFrame for MyList {
 }
 
 impl Widget for &MyList {
-    fn render(self, area: Rect, buf: &mut Buffer)
+    fn render(self, _area: Rect, _buf: &mut Buffer)
     where
         Self: Sized,
     {
@@ -259,44 +321,85 @@ impl StatefulWidget for &MyList {
     type State = (usize, Vec<RcRc<Note>>);
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         let title_text = if self.is_active {
-            " Note List".green().bold()
+            " Note List".fg(theme().text).bold()
         } else {
-            " Note List ".green().dim()
+            " Note List ".fg(theme().text).dim()
         };
         let title = Title::from(title_text);
         let mut block = Block::bordered()
             .title(title.alignment(Alignment::Center))
             .border_set(symbols::border::ROUNDED);
         if self.is_active {
-            block = block.set_style(Color::White);
+            block = block.set_style(theme().active);
         } else {
-            block = block.set_style(Color::Green)
+            block = block.set_style(theme().inactive)
         }
-        let list: Vec<String>;
-        if self.is_search {
-            list = self
-                .filter_list(self.search.clone())
-                .unwrap()
+        if self.is_search && self.full_text {
+            let results = search(&self.notes, self.search.as_deref().unwrap_or(""));
+            let rows = results.len() * 2;
+            let lines: Vec<text::Line> = results
                 .iter()
-                .map(|note| note.borrow().title.to_string())
+                .enumerate()
+                .flat_map(|(count, result)| {
+                    let colour = if state.0 == count {
+                        theme().selected
+                    } else {
+                        theme().text
+                    };
+                    [
+                        text::Line::raw(result.note.borrow().title.clone()).style(colour),
+                        snippet_line(result.snippet.as_ref()),
+                    ]
+                })
                 .collect();
+            Paragraph::new(lines)
+                .left_aligned()
+                .block(block)
+                .bg(Color::Black)
+                .scroll((self.scroll as u16, 0))
+                .render(area, buf);
+            render_scrollbar(area, buf, self.scroll, rows);
+            return;
+        }
+        let list: Vec<(String, Color)> = if self.is_search {
+            self.results()
+                .iter()
+                .map(|note| (note.borrow().title.to_string(), theme().text))
+                .collect()
         } else {
-            list = state
-                .1
+            self.entries()
                 .iter()
-                .map(|note| note.borrow_mut().title.to_string())
-                .collect();
+                .map(|entry| match entry {
+                    ListEntry::Folder {
+                        path,
+                        depth,
+                        collapsed,
+                    } => (
+                        format!(
+                            "{}{} {}",
+                            "  ".repeat(*depth),
+                            if *collapsed { "▸" } else { "▾" },
+                            path.file_name().unwrap_or_default().to_string_lossy()
+                        ),
+                        theme().folder,
+                    ),
+                    ListEntry::Note { note, depth } => (
+                        "  ".repeat(*depth) + &note.borrow().title,
+                        theme().text,
+                    ),
+                })
+                .collect()
         };
-        let mut count = 0;
+        let rows = list.len();
         let text: Vec<text::Line> = list
             .iter()
-            .map(|title| {
+            .enumerate()
+            .map(|(count, (title, colour))| {
                 let colour = if state.0 == count {
-                    Color::Blue
+                    theme().selected
                 } else {
-                    Color::Green
+                    *colour
                 };
-                count += 1;
                 text::Line::raw(title).style(colour)
             })
             .collect();
@@ -304,50 +407,204 @@ impl StatefulWidget for &MyList {
             .left_aligned()
             .block(block)
             .bg(Color::Black)
+            .scroll((self.scroll as u16, 0))
             .render(area, buf);
+        render_scrollbar(area, buf, self.scroll, rows);
     }
 }
 
+// a dimmed line of context under a text search result, the match picked out
+fn snippet_line(snippet: Option<&(String, usize, usize)>) -> text::Line<'static> {
+    let Some((line, start, end)) = snippet else {
+        return text::Line::raw("");
+    };
+    // start a little before the match so it shows in a narrow panel
+    let from = line[..*start]
+        .char_indices()
+        .rev()
+        .nth(11)
+        .map_or(0, |(index, _)| index);
+    let prefix = if from > 0 { "  …" } else { "  " };
+    text::Line::from(vec![
+        (prefix.to_string() + &line[from..*start]).dim(),
+        line[*start..*end]
+            .to_string()
+            .bg(theme().highlight)
+            .fg(Color::Black),
+        line[*end..].to_string().dim(),
+    ])
+}
+
 impl MyList {
-    pub fn filter_list(&self, search: Option<String>) -> Option<Vec<RcRc<Note>>> {
-        let search_tags: Vec<Tag> = match search {
-            Some(tags) => tags
-                .split_whitespace()
-                .map(|tag| Tag("#".to_string() + tag))
-                .collect(),
-            None => return Some(self.notes.clone()),
+    // load every note under the configured vault path, along with any that failed to load
+    pub fn new(settings: &Settings) -> Result<(Self, Vec<Error>)> {
+        let (found_notes, skipped) =
+            get_notes(&settings.path, &settings.extension, &settings.templates)?;
+        let cwd = std::env::current_dir().map_err(|err| Error::io(Path::new("."), err))?;
+        let list = MyList {
+            notes: found_notes,
+            index: 0,
+            path: settings.path.clone(),
+            is_active: true,
+            is_search: false,
+            search: None,
+            full_text: false,
+            tag_all: false,
+            local_list: false,
+            local_path: cwd,
+            local_dir: settings.local_dir.clone(),
+            sort: settings.sort,
+            collapsed: HashSet::new(),
+            scroll: 0,
         };
-        if search_tags.is_empty() {
-            return Some(self.notes.clone());
+        Ok((list, skipped))
+    }
+
+    // entries in the panel, search results while searching
+    pub fn rows(&self) -> usize {
+        if self.is_search {
+            self.results().len()
+        } else {
+            self.entries().len()
+        }
+    }
+
+    // screen rows per entry, text search shows a snippet under each title
+    pub fn entry_height(&self) -> usize {
+        if self.is_search && self.full_text {
+            2
+        } else {
+            1
+        }
+    }
+
+    // notes matching the search box, ranked best first for a text search
+    pub fn results(&self) -> Vec<RcRc<Note>> {
+        if self.full_text {
+            search(&self.notes, self.search.as_deref().unwrap_or(""))
+                .into_iter()
+                .map(|result| result.note)
+                .collect()
+        } else {
+            self.filter_list(self.search.clone())
+        }
+    }
+
+    // the directory notes are currently loaded from
+    pub fn root(&self) -> PathBuf {
+        if self.local_list {
+            self.local_path.join(&self.local_dir)
+        } else {
+            self.path.clone()
         }
+    }
 
-        let mut notes = self.notes.clone();
+    // flatten the folder tree into the rows currently visible
+    pub fn entries(&self) -> Vec<ListEntry> {
+        let mut entries = vec![];
+        self.push_folder(Path::new(""), 0, &mut entries);
+        entries
+    }
 
-        notes = match !self.tag_all {
-            true => notes
+    fn push_folder(&self, folder: &Path, depth: usize, entries: &mut Vec<ListEntry>) {
+        let mut sub_folders: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut notes: Vec<RcRc<Note>> = vec![];
+        for note in self.notes.iter() {
+            let note_folder = note.borrow().folder.clone();
+            if note_folder == folder {
+                notes.push(note.to_owned());
+            } else if let Some(child) = note_folder
+                .strip_prefix(folder)
+                .ok()
+                .and_then(|rest| rest.components().next())
+            {
+                sub_folders.insert(folder.join(child));
+            }
+        }
+        for path in sub_folders {
+            let collapsed = self.collapsed.contains(&path);
+            entries.push(ListEntry::Folder {
+                path: path.clone(),
+                depth,
+                collapsed,
+            });
+            if !collapsed {
+                self.push_folder(&path, depth + 1, entries);
+            }
+        }
+        match self.sort {
+            Sort::Title => notes.sort_by_key(|note| note.borrow().title.to_lowercase()),
+            // newest first
+            Sort::Modified => notes.sort_by_key(|note| std::cmp::Reverse(note.borrow().modified)),
+            Sort::Created => notes.sort_by_key(|note| std::cmp::Reverse(note.borrow().created)),
+        }
+        for note in notes {
+            entries.push(ListEntry::Note { note, depth });
+        }
+    }
+
+    // find the note a link points at by vault path, then by title, then by alias
+    pub fn resolve(&self, link: &Link) -> Option<RcRc<Note>> {
+        if link.target.is_empty() || link.is_external() {
+            return None;
+        }
+        let target = link.target.trim_start_matches('/').to_lowercase();
+        let title = target.rsplit('/').next().unwrap_or(&target).to_string();
+        let by_path = |note: &&RcRc<Note>| {
+            let note = note.borrow();
+            note.folder.join(&note.title).to_string_lossy().to_lowercase() == target
+        };
+        let by_title = |note: &&RcRc<Note>| note.borrow().title.to_lowercase() == title;
+        let by_alias = |note: &&RcRc<Note>| {
+            note.borrow()
+                .frontmatter
+                .aliases
                 .iter()
-                .filter(|note| {
-                    note.borrow()
-                        .tags
-                        .clone()
-                        .is_some_and(|tags| tags.iter().any(|tag| search_tags.contains(tag)))
-                })
-                .map(|note| note.to_owned())
-                .collect(),
-            false => notes
+                .any(|alias| alias.to_lowercase() == title)
+        };
+        self.notes
+            .iter()
+            .find(by_path)
+            .or_else(|| self.notes.iter().find(by_title))
+            .or_else(|| self.notes.iter().find(by_alias))
+            .cloned()
+    }
+
+    pub fn selected_note(&self) -> Option<RcRc<Note>> {
+        match self.entries().get(self.index) {
+            Some(ListEntry::Note { note, .. }) => Some(note.to_owned()),
+            _ => None,
+        }
+    }
+
+    // folder new notes are created in, taken from the selected row
+    pub fn selected_folder(&self) -> PathBuf {
+        match self.entries().get(self.index) {
+            Some(ListEntry::Folder { path, .. }) => path.to_owned(),
+            Some(ListEntry::Note { note, .. }) => note.borrow().folder.clone(),
+            None => PathBuf::new(),
+        }
+    }
+
+    // notes the query in the search box picks out, all of them for a blank one and none for a bad one
+    pub fn filter_list(&self, search: Option<String>) -> Vec<RcRc<Note>> {
+        match query::parse(search.as_deref().unwrap_or(""), self.tag_all) {
+            Ok(None) => self.notes.clone(),
+            Ok(Some(query)) => self
+                .notes
                 .iter()
-                .filter(|note| {
-                    note.borrow()
-                        .tags
-                        .clone()
-                        .is_some_and(|tags| search_tags.iter().all(|search| tags.contains(search)))
-                })
+                .filter(|note| query.matches(&note.borrow()))
                 .map(|note| note.to_owned())
                 .collect(),
-        };
-        if notes.is_empty() {
-            return Some(self.notes.clone());
+            Err(_) => vec![],
+        }
+    }
+
+    // why the query in the search box doesn't parse
+    pub fn query_error(&self) -> Option<String> {
+        if !self.is_search || self.full_text {
+            return None;
         }
-        Some(notes)
+        query::parse(self.search.as_deref().unwrap_or(""), self.tag_all).err()
     }
 }
@@ -1,32 +1,98 @@
+use agenda::Agenda;
 use app::{App, CurrentFrame};
+use backlinks::Backlinks;
+use error::Error;
+use journal::Period;
 use list::MyList;
 use note::Note;
+use popup::Popup;
+use settings::Settings;
+use tags::TagTree;
 use utils::rc_rc;
-use std::
-    io::{self, Result}
-;
-use traits::ThisFrame;
+use std::collections::HashMap;
+use std::io;
+mod agenda;
 mod app;
+mod backlinks;
+mod code;
+mod error;
 mod file_reader;
+mod finder;
+mod frontmatter;
+mod history;
+mod journal;
 mod list;
+mod markdown;
 mod note;
+mod popup;
+mod query;
+mod rename;
+mod search;
+mod settings;
+mod swap;
+mod tags;
+mod templates;
 mod traits;
 mod tui;
 mod utils;
+mod watcher;
+mod wrap;
 
 
 fn main() -> io::Result<()> {
-    let mut terminal = tui::init().unwrap();
+    // noter --daily opens today's journal note straight away
+    let period = match std::env::args().nth(1).as_deref() {
+        None => None,
+        Some("--daily" | "--today") => Some(Period::Daily),
+        Some("--weekly") => Some(Period::Weekly),
+        Some("--monthly") => Some(Period::Monthly),
+        Some(arg) => {
+            eprintln!("unknown argument `{}`, expected --daily, --weekly or --monthly", arg);
+            std::process::exit(2);
+        }
+    };
+    tui::install_panic_hook();
+    let mut terminal = tui::init()?;
+    // a bad config or vault still opens the app, with the error shown over an empty list
     let mut app = App {
+        settings: Settings::default(),
         current_frame: CurrentFrame::List,
         note: rc_rc(Note::new()),
         input_mode: false,
         cursor_row: 0,
         cursor_column: 0,
         exit: false,
-        note_list: MyList::new(),
+        note_list: MyList {
+            is_active: true,
+            ..Default::default()
+        },
+        backlinks: Backlinks::default(),
+        popup: None,
+        history: HashMap::new(),
+        note_height: 0,
+        note_width: 0,
+        list_height: 0,
+        wrap: true,
+        pending_key: None,
+        open_in_editor: false,
+        highlight: None,
+        last_edit: None,
+        swapped: false,
+        watcher: None,
+        tag_tree: TagTree::default(),
+        agenda: Agenda::default(),
     };
+    match Settings::load() {
+        Ok(settings) => {
+            app.load_settings(settings);
+            if let Some(period) = period {
+                app.open_journal(period, journal::today());
+            }
+        }
+        Err(Error::NoConfig(_)) => app.popup = Some(Popup::setup(period)),
+        Err(err) => app.report(err),
+    }
     let app_result = app.run(&mut terminal);
-    tui::restore().unwrap();
+    tui::restore()?;
     app_result
 }
@@ -0,0 +1,380 @@
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use regex::Regex;
+
+use crate::code;
+use crate::file_reader::get_links;
+use crate::search::Match;
+use crate::settings::theme;
+
+// a fenced code block, the body runs between the fence lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fence {
+    pub language: String,
+    pub body: Range<usize>,
+}
+
+// the fenced code blocks of a text, an unclosed fence runs to the end
+pub fn fences(text: &str) -> Vec<Fence> {
+    let mut fences = vec![];
+    // the fence character, length, row and language of the open block
+    let mut open: Option<(char, usize, usize, String)> = None;
+    let mut rows = 0;
+    for (row, line) in text.split('\n').enumerate() {
+        rows += 1;
+        let Some((found, found_len)) = fence_of(line) else {
+            continue;
+        };
+        match open.take() {
+            None => {
+                let info = &line.trim_start_matches(' ')[found_len..];
+                let language = info.split_whitespace().next().unwrap_or("").to_string();
+                open = Some((found, found_len, row, language));
+            }
+            Some((c, len, start, language)) if c == found && found_len >= len => {
+                fences.push(Fence {
+                    language,
+                    body: start + 1..row,
+                });
+            }
+            still_open => open = still_open,
+        }
+    }
+    if let Some((_, _, start, language)) = open {
+        fences.push(Fence {
+            language,
+            body: start + 1..rows,
+        });
+    }
+    fences
+}
+
+// which lines of the text sit in a fenced code block, the fences included
+pub fn fenced_rows(text: &str) -> Vec<bool> {
+    let count = text.split('\n').count();
+    let mut rows = vec![false; count];
+    for fence in fences(text) {
+        rows[fence.body.start - 1..(fence.body.end + 1).min(count)].fill(true);
+    }
+    rows
+}
+
+// a ``` or ~~~ line indented at most three spaces
+fn fence_of(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|found| *found == c).count();
+    // an info string can't hold a backtick, or the line is an inline code span
+    let info = &trimmed[len..];
+    (len >= 3 && !(c == '`' && info.contains('`'))).then_some((c, len))
+}
+
+// byte ranges of the `inline code` spans in a line, backticks included
+pub fn code_spans(line: &str) -> Vec<(usize, usize)> {
+    let bytes = line.as_bytes();
+    let run_at = |start: usize| bytes[start..].iter().take_while(|b| **b == b'`').count();
+    let mut spans = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'`' {
+            index += 1;
+            continue;
+        }
+        let len = run_at(index);
+        // the span closes at the next run of exactly as many backticks
+        let mut close = index + len;
+        let end = loop {
+            match bytes[close..].iter().position(|b| *b == b'`') {
+                Some(offset) => {
+                    let found = close + offset;
+                    let found_len = run_at(found);
+                    if found_len == len {
+                        break Some(found + found_len);
+                    }
+                    close = found + found_len;
+                }
+                None => break None,
+            }
+        };
+        match end {
+            Some(end) => {
+                spans.push((index, end));
+                index = end;
+            }
+            // an unmatched run is plain text
+            None => index += len,
+        }
+    }
+    spans
+}
+
+// bytes taken by a heading's leading #s, None when the line isn't a heading
+pub fn heading_marker(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start_matches(' ');
+    let indent = line.len() - trimmed.len();
+    let level = trimmed.bytes().take_while(|b| *b == b'#').count();
+    let rest = &trimmed[level..];
+    let heading = indent <= 3
+        && (1..=6).contains(&level)
+        && (rest.is_empty() || rest.starts_with([' ', '\t']));
+    heading.then_some(indent + level)
+}
+
+// letters, digits, _, - and / for nesting, anything else ends the tag
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '/'
+}
+
+// the #tags of a text as byte ranges, # included, outside code and heading markers
+// a tag starts a line or follows whitespace, so url fragments and [[note#heading]] don't count
+pub fn find_tags(text: &str) -> Vec<Match> {
+    let fenced = fenced_rows(text);
+    let mut tags = vec![];
+    for (row, line) in text.split('\n').enumerate() {
+        if fenced[row] {
+            continue;
+        }
+        let code = code_spans(line);
+        let skip = heading_marker(line).unwrap_or(0);
+        let mut previous: Option<char> = None;
+        for (start, c) in line.char_indices() {
+            let after_space = previous.is_none_or(char::is_whitespace);
+            previous = Some(c);
+            if c != '#' || start < skip || !after_space {
+                continue;
+            }
+            if code.iter().any(|(from, to)| *from <= start && start < *to) {
+                continue;
+            }
+            let body: String = line[start + 1..].chars().take_while(|c| is_tag_char(*c)).collect();
+            // trailing slashes are punctuation, and a tag needs more than digits (#1 is an issue)
+            let body = body.trim_end_matches('/');
+            if body.is_empty() || body.starts_with('/') || body.chars().all(|c| c.is_numeric()) {
+                continue;
+            }
+            tags.push(Match {
+                row,
+                start,
+                end: start + 1 + body.len(),
+            });
+        }
+    }
+    tags
+}
+
+static BULLET: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*)([-*+]|\d{1,9}[.)])(?:\s+(\[([ xX])\]))?(\s|$)").unwrap());
+
+// style the raw markdown line by line, markers stay visible so columns match the source
+pub fn highlight(text: &str) -> Vec<Line<'static>> {
+    let fenced = fenced_rows(text);
+    let tags = find_tags(text);
+    let links = get_links(text);
+    let lines: Vec<&str> = text.split('\n').collect();
+    // blocks in a language we know, highlighted as a whole
+    let mut code_lines: Vec<Option<Line<'static>>> = vec![None; lines.len()];
+    for fence in fences(text).into_iter().filter(|fence| !fence.body.is_empty()) {
+        let body = lines[fence.body.clone()].join("\n");
+        if let Some(highlighted) = code::highlight(&fence.language, &body) {
+            for (offset, line) in highlighted.into_iter().enumerate() {
+                code_lines[fence.body.start + offset] = Some(line);
+            }
+        }
+    }
+    let text_style = Style::new().fg(theme().text);
+    let marker = Style::new().fg(theme().marker);
+    text.split('\n')
+        .enumerate()
+        .map(|(row, line)| {
+            let mut styles = vec![text_style; line.len()];
+            let mut paint = |start: usize, end: usize, style: Style| {
+                for byte in styles[start..end].iter_mut() {
+                    *byte = byte.patch(style);
+                }
+            };
+            if let Some(line) = &code_lines[row] {
+                return line.clone();
+            }
+            if fenced[row] {
+                let style = if fence_of(line).is_some() {
+                    marker
+                } else {
+                    Style::new().fg(theme().code)
+                };
+                paint(0, line.len(), style);
+                return to_line(line, &styles);
+            }
+            if let Some(end) = heading_marker(line) {
+                let level = line[..end].trim_start().len();
+                let modifier = match level {
+                    1 => Modifier::BOLD | Modifier::UNDERLINED,
+                    2 => Modifier::BOLD,
+                    3 => Modifier::BOLD | Modifier::ITALIC,
+                    _ => Modifier::empty(),
+                };
+                paint(0, line.len(), Style::new().fg(theme().heading).add_modifier(modifier));
+                paint(0, end, marker);
+            } else {
+                // > bars, then a bullet and maybe a checkbox
+                let mut start = 0;
+                while let Some(bar) = line[start..].trim_start_matches(' ').strip_prefix('>') {
+                    let end = line.len() - bar.len();
+                    paint(start, end, marker);
+                    start = end;
+                }
+                if start > 0 {
+                    paint(start, line.len(), Style::new().fg(theme().quote).add_modifier(Modifier::ITALIC));
+                }
+                if let Some(found) = BULLET.captures(&line[start..]) {
+                    let item = found.get(2).unwrap();
+                    paint(start + item.start(), start + item.end(), marker);
+                    if let (Some(checkbox), Some(state)) = (found.get(3), found.get(4)) {
+                        let (from, to) = (start + checkbox.start(), start + checkbox.end());
+                        if state.as_str() == " " {
+                            paint(from, to, Style::new().fg(theme().task).add_modifier(Modifier::BOLD));
+                        } else {
+                            paint(from, to, Style::new().fg(theme().done));
+                            let done = Style::new().fg(theme().done).add_modifier(Modifier::CROSSED_OUT);
+                            paint(to, line.len(), done);
+                        }
+                    }
+                }
+            }
+            // inline markup, none of it inside code spans
+            let code = code_spans(line);
+            let in_code = |at: usize| code.iter().any(|(from, to)| *from <= at && at < *to);
+            for (delimiter, modifier) in [
+                ("**", Modifier::BOLD),
+                ("__", Modifier::BOLD),
+                ("*", Modifier::ITALIC),
+                ("_", Modifier::ITALIC),
+                ("~~", Modifier::CROSSED_OUT),
+            ] {
+                for (from, to) in delimited(line, delimiter) {
+                    if in_code(from) {
+                        continue;
+                    }
+                    paint(from, to, Style::new().add_modifier(modifier));
+                    paint(from, from + delimiter.len(), marker);
+                    paint(to - delimiter.len(), to, marker);
+                }
+            }
+            for link in links.iter().filter(|link| link.row == row) {
+                if in_code(link.start) {
+                    continue;
+                }
+                let link_style = Style::new().fg(theme().link).add_modifier(Modifier::UNDERLINED);
+                paint(link.start, link.end, link_style);
+                // the (url) of a [text](url) link
+                if let Some(url) = line[link.start..link.end].rfind("](").filter(|_| !link.wiki) {
+                    let url_style = Style::new()
+                        .add_modifier(Modifier::DIM)
+                        .remove_modifier(Modifier::UNDERLINED);
+                    paint(link.start + url + 1, link.end, url_style);
+                }
+            }
+            for tag in tags.iter().filter(|tag| tag.row == row) {
+                paint(tag.start, tag.end, Style::new().fg(theme().tag));
+            }
+            for (from, to) in code.iter() {
+                paint(*from, *to, Style::new().fg(theme().code));
+            }
+            to_line(line, &styles)
+        })
+        .collect()
+}
+
+// byte ranges wrapped in a delimiter run such as ** or ~~, delimiters included
+// a run only counts at exactly the delimiter's length, so * never matches inside **
+fn delimited(line: &str, delimiter: &str) -> Vec<(usize, usize)> {
+    let c = delimiter.as_bytes()[0];
+    let len = delimiter.len();
+    let bytes = line.as_bytes();
+    let mut ranges = vec![];
+    let mut open: Option<usize> = None;
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != c {
+            index += 1;
+            continue;
+        }
+        let run = bytes[index..].iter().take_while(|b| **b == c).count();
+        if run == len {
+            let before = line[..index].chars().next_back();
+            let after = line[index + len..].chars().next();
+            // snake_case words keep their underscores
+            let word = |side: Option<char>| c == b'_' && side.is_some_and(char::is_alphanumeric);
+            let opens = after.is_some_and(|after| !after.is_whitespace()) && !word(before);
+            let closes = before.is_some_and(|before| !before.is_whitespace()) && !word(after);
+            match open {
+                Some(start) if closes => {
+                    ranges.push((start, index + len));
+                    open = None;
+                }
+                None if opens => open = Some(index),
+                _ => {}
+            }
+        }
+        index += run;
+    }
+    ranges
+}
+
+// join runs of bytes sharing a style into spans
+fn to_line(line: &str, styles: &[Style]) -> Line<'static> {
+    let mut spans = vec![];
+    let mut start = 0;
+    for index in 1..=line.len() {
+        if index == line.len() || styles[index] != styles[start] {
+            spans.push(Span::styled(line[start..index].to_string(), styles[start]));
+            start = index;
+        }
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(text: &str) -> Vec<String> {
+        let lines: Vec<&str> = text.split('\n').collect();
+        find_tags(text)
+            .iter()
+            .map(|tag| lines[tag.row][tag.start..tag.end].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn tags_after_whitespace() {
+        let text = "#one two #project/noter/ ,#no\n\t#über_tag-2.";
+        assert_eq!(tags(text), ["#one", "#project/noter", "#über_tag-2"]);
+    }
+
+    #[test]
+    fn not_tags() {
+        let text = "# Heading\n## #real\n[[Note#Anchor]] a.b/#frag #12 #/x\n`#code`\n```\n#fenced\n```";
+        assert_eq!(tags(text), ["#real"]);
+    }
+
+    #[test]
+    fn delimiter_runs() {
+        let line = "**bold** and *it* in ***both***";
+        assert_eq!(delimited(line, "**"), [(0, 8)]);
+        assert_eq!(delimited(line, "*"), [(13, 17)]);
+        assert_eq!(delimited("~~gone~~ ~~ not ~~", "~~"), [(0, 8)]);
+        assert_eq!(delimited("snake_case_name and _this_", "_"), [(20, 26)]);
+        assert!(delimited("2 * 3 * 4", "*").is_empty());
+    }
+
+    #[test]
+    fn inline_code_spans() {
+        assert_eq!(code_spans("a `b` ``c ` d`` `open"), [(2, 5), (6, 15)]);
+    }
+}
@@ -1,44 +1,137 @@
+use std::io;
 use std::path::PathBuf;
-use std::str::FromStr;
-use std::sync::mpsc::Receiver;
-use std::{fmt::Display, fs, path::Path};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use std::{fmt::Display, path::Path};
 
-use crate::file_reader::parse_file;
+use crate::file_reader::{get_links, get_tags_links, get_tasks, OnDisk};
+use crate::frontmatter::Frontmatter;
+use crate::history;
+use crate::journal;
+use crate::markdown::{self, heading_marker};
+use crate::popup::Popup;
+use crate::rename::{self, Rename};
+use crate::search::{find_matches, highlight_line, matcher};
+use crate::settings::{theme, Keymap};
+use crate::utils::{byte_index, column_of, grapheme_len, render_scrollbar};
+use crate::wrap::{column_at, cursor_row, cursor_x, slice_line, visual_rows};
 use crate::{
     app::{App, CurrentFrame, InputMode},
-    file_reader::write_file,
+    error::Error,
     traits::ThisFrame,
 };
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
 use ratatui::{
-    layout::{Alignment, Rect},
-    style::{Color, Styled, Stylize},
+    layout::Alignment,
+    style::{Color, Style, Styled, Stylize},
     symbols::border,
-    text::{Line, Span, Text},
-    widgets::{
-        block::{Position, Title},
-        Block, Paragraph, Widget, Wrap,
-    },
+    text::{Line, Text},
+    widgets::{block::Title, Block, Paragraph, Widget},
 };
-use regex::Regex;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 #[repr(transparent)]
 pub struct Tag(pub String);
 
-#[derive(Debug, Clone)]
-pub struct Link(pub String);
+impl Tag {
+    // the tag without its #, nested levels separated by /
+    pub fn path(&self) -> &str {
+        self.0.trim_start_matches('#').trim_matches('/')
+    }
+
+    // a tag sits under itself and every tag above it, so #project holds #project/noter/ui
+    pub fn is_under(&self, parent: &str) -> bool {
+        let path = self.path().to_lowercase();
+        let parent = parent.trim_start_matches('#').trim_matches('/').to_lowercase();
+        path == parent || path.starts_with(&(parent + "/"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    // note title or vault relative path, without the extension
+    pub target: String,
+    pub alias: Option<String>,
+    // heading within the target note
+    pub anchor: Option<String>,
+    pub wiki: bool,
+    // where the link sits in the note text, columns are a byte range of the line
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+// most urgent first, so sorting puts them at the top
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Highest,
+    High,
+    Medium,
+    Low,
+    Lowest,
+}
+
+// a `- [ ]` checkbox item
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub row: usize,
+    pub done: bool,
+    // the item after its checkbox
+    pub text: String,
+    // YYYY-MM-DD, from 📅 or due:
+    pub due: Option<String>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<Tag>,
+}
+
+impl Link {
+    pub fn is_external(&self) -> bool {
+        self.target.contains("://") || self.target.starts_with("mailto:")
+    }
+}
+
+// a note's identity for this run, taken when it's created or read and kept through renames and
+// reloads. notes that share a path at different times, or have no title yet, stay apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoteKey(u64);
+
+impl Default for NoteKey {
+    fn default() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        NoteKey(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
 
 #[derive(Debug, Default, Clone)]
 pub struct Note {
+    pub key: NoteKey,
     pub title: String,
     pub text: String,
     pub links: Option<Vec<Link>>,
     pub tags: Option<Vec<Tag>>,
+    pub tasks: Option<Vec<Task>>,
     pub mode: InputMode,
     pub edited: bool,
     pub is_active: bool,
     pub old_title: Option<String>,
+    // folder relative to the vault root, so notes sharing a title stay distinct
+    pub folder: PathBuf,
+    pub frontmatter: Frontmatter,
+    pub modified: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    // the file as last read or written, None until the note has been saved
+    pub disk: Option<OnDisk>,
+    // the file changed or went away elsewhere while there were edits here or while it was open,
+    // cleared once noter writes it again
+    pub changed: bool,
+    // first screen row shown, counted in wrapped rows when wrapping
+    pub scroll: usize,
+    // soft wrap when drawn, the app sets this on the copy it renders
+    pub wrap: bool,
+    // text search to pick out when drawn, also set by the app
+    pub highlight: Option<String>,
 }
 impl Display for Note {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -48,21 +141,38 @@ impl Display for Note {
 
 impl ThisFrame for Note {
     // get key bindings for this mode.
-    fn get_instructions(&self) -> Title {
+    fn get_instructions(&self, keymap: &Keymap) -> Title<'_> {
+        let key = |c: char| format!("<{}>", c).blue().bold();
         match self.mode {
             InputMode::Normal => Title::from(Line::from(vec![
                 " Back ".into(),
                 "<esc>".blue().bold(),
                 " Edit Title ".into(),
-                "<t>".blue().bold(),
+                key(keymap.edit_title),
                 " Save Note ".into(),
-                "<s>".blue().bold(),
+                key(keymap.save),
                 " Search Tags ".into(),
-                "<T>".blue().bold(),
+                key(keymap.search_tags),
                 " Insert Mode  ".into(),
-                "<i>".blue().bold(),
+                key(keymap.insert),
+                " Follow Link ".into(),
+                "<return>".blue().bold(),
+                " Backlinks ".into(),
+                key(keymap.backlinks),
+                " Wrap ".into(),
+                key(keymap.wrap),
+                " $EDITOR ".into(),
+                key(keymap.external_editor),
+                " Toggle Task ".into(),
+                key(keymap.toggle_task),
+                " Prev/Next ".into(),
+                format!("<{}/{}>", keymap.prev_period, keymap.next_period).blue().bold(),
+                " Undo ".into(),
+                key(keymap.undo),
+                " Redo ".into(),
+                format!("<C-{}>", keymap.redo).blue().bold(),
                 " Quit ".into(),
-                "<q>".blue().bold(),
+                key(keymap.quit),
             ])),
             InputMode::Insert => Title::from(Line::from(vec![
                 " Normal Mode ".into(),
@@ -74,44 +184,164 @@ impl ThisFrame for Note {
             ])),
         }
     }
-    fn new() -> Note {
-        Note {
-            title: "Enter Title".to_string(),
-            text: "Text Here".to_string(),
-            links: None,
-            tags: None,
-            mode: InputMode::Normal,
-            edited: false,
-            is_active: true,
-            old_title: None,
-        }
-    }
     fn get_type(&self) -> String {
         "Note".to_owned()
     }
+    // a key that can't move the cursor stops at its own arm rather than falling through
+    #[allow(clippy::collapsible_match)]
     fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) {
+        let keymap = app.settings.keymap.clone();
+        let pending_key = app.pending_key.take();
         let mut note = app.note.borrow_mut();
         match (key_event.code, &self.mode) {
-            (KeyCode::Char('q'), InputMode::Normal) => app.exit = true,
-            (KeyCode::Char('i'), InputMode::Normal) => {
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.quit => {
+                drop(note);
+                app.quit();
+            }
+            (KeyCode::Char(c), InputMode::Normal)
+                if c == keymap.redo && key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                let cursor = (app.cursor_row, app.cursor_column);
+                if let Some((text, cursor)) =
+                    history::of(&mut app.history, &note).redo(&note.text, cursor)
+                {
+                    note.restore(text);
+                    (app.cursor_row, app.cursor_column) = cursor;
+                }
+            }
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.undo => {
+                let cursor = (app.cursor_row, app.cursor_column);
+                if let Some((text, cursor)) =
+                    history::of(&mut app.history, &note).undo(&note.text, cursor)
+                {
+                    note.restore(text);
+                    (app.cursor_row, app.cursor_column) = cursor;
+                }
+            }
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.insert => {
                 note.mode = InputMode::Insert;
+                history::of(&mut app.history, &note).begin((app.cursor_row, app.cursor_column));
             }
-            (KeyCode::Char('s'), InputMode::Normal) => {
-                if note.edited {
-                    if app.note_list.local_list {
-                        write_file(Some(app.note_list.local_path.clone()), &mut note.clone());
-                    } else {
-                        write_file(None, &mut note.clone());
-                    }
-                    note.edited = false;
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.save => {
+                if !note.edited {
+                    return;
+                }
+                drop(note);
+                let note = app.note.clone();
+                app.save(&note);
+            }
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.wrap => app.wrap = !app.wrap,
+            (KeyCode::Char(c), InputMode::Normal)
+                if c == keymap.prev_period || c == keymap.next_period =>
+            {
+                let Some((period, date)) = journal::period_of(&note, &app.settings.journal) else {
+                    return;
+                };
+                drop(note);
+                // skip days without a note, stepping one period at the ends
+                let forward = c == keymap.next_period;
+                let journal = &app.settings.journal;
+                let date = journal::neighbour(&app.note_list.notes, journal, period, date, forward)
+                    .unwrap_or_else(|| period.step(date, forward));
+                app.open_journal(period, date);
+            }
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.toggle_task => {
+                let cursor = (app.cursor_row, app.cursor_column);
+                // a note without history yet takes the text before the toggle as its first step
+                history::of(&mut app.history, &note);
+                if note.toggle_task(app.cursor_row) {
+                    history::of(&mut app.history, &note).commit(&note.text, cursor);
+                }
+            }
+            (KeyCode::Char(c), InputMode::Normal)
+                if c == keymap.next_match || c == keymap.prev_match =>
+            {
+                let Some(regex) = app.highlight.as_deref().and_then(matcher) else {
+                    return;
+                };
+                let matches = find_matches(&note.text, &regex);
+                let lines: Vec<&str> = note.text.split('\n').collect();
+                let here = (
+                    app.cursor_row,
+                    byte_index(lines.get(app.cursor_row).unwrap_or(&""), app.cursor_column),
+                );
+                // wrap around the ends of the note like vim does
+                let found = if c == keymap.next_match {
+                    matches
+                        .iter()
+                        .find(|found| (found.row, found.start) > here)
+                        .or(matches.first())
+                } else {
+                    matches
+                        .iter()
+                        .rev()
+                        .find(|found| (found.row, found.start) < here)
+                        .or(matches.last())
+                };
+                if let Some(found) = found {
+                    app.cursor_row = found.row;
+                    app.cursor_column = column_of(lines[found.row], found.start);
                 }
             }
-            (KeyCode::Char('t'), InputMode::Normal) => {
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.external_editor => {
+                // the editor works on the file, so it has to hold what's on screen first
+                if note.title.is_empty() {
+                    drop(note);
+                    app.report(Error::Untitled);
+                    return;
+                }
+                let edited = note.edited;
+                drop(note);
+                let note = app.note.clone();
+                if edited && !app.save(&note) {
+                    return;
+                }
+                app.open_in_editor = true;
+            }
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.edit_title => {
                 note.mode = InputMode::EditTitle;
                 note.old_title = Some(self.title.clone());
                 app.cursor_column = 0;
             }
-            (KeyCode::Char('T'), InputMode::Normal) => {
+            (KeyCode::Enter, InputMode::Normal) => {
+                let line = note.line(app.cursor_row);
+                let index = byte_index(line, app.cursor_column);
+                let Some(link) = get_links(line)
+                    .into_iter()
+                    .find(|link| (link.start..link.end).contains(&index))
+                else {
+                    return;
+                };
+                // links to a heading in this same note just move the cursor
+                let target = if link.target.is_empty() {
+                    None
+                } else {
+                    match app.note_list.resolve(&link) {
+                        Some(target) => Some(target),
+                        None => return,
+                    }
+                };
+                let anchor = link.anchor.unwrap_or_default();
+                match target {
+                    Some(target) => {
+                        drop(note);
+                        let row = target.borrow().heading_row(&anchor).unwrap_or(0);
+                        app.open(target, (row, 0));
+                    }
+                    None => {
+                        app.cursor_row = note.heading_row(&anchor).unwrap_or(app.cursor_row);
+                    }
+                }
+                app.cursor_column = 0;
+            }
+            (KeyCode::Char(c), InputMode::Normal)
+                if c == keymap.backlinks && !app.backlinks.entries.is_empty() =>
+            {
+                app.backlinks.is_active = true;
+                app.backlinks.index = 0;
+                app.current_frame = CurrentFrame::Backlinks;
+            }
+            (KeyCode::Char(c), InputMode::Normal) if c == keymap.search_tags => {
                 app.note_list.is_active = true;
                 app.current_frame = CurrentFrame::List;
                 app.note_list.is_search = true;
@@ -121,104 +351,88 @@ impl ThisFrame for Note {
             (KeyCode::Backspace, InputMode::EditTitle) => {
                 if app.cursor_column == 0 {
                 } else {
-                    note.edited = true;
-                    let (first, second) = note.title.as_mut_str().split_at(app.cursor_column);
                     app.cursor_column = app.cursor_column.saturating_sub(1);
-                    note.title = first.split_at(app.cursor_column).0.to_string() + second;
+                    let start = byte_index(&note.title, app.cursor_column);
+                    let end = byte_index(&note.title, app.cursor_column + 1);
+                    note.title.replace_range(start..end, "");
                 }
             }
             (KeyCode::Char(c), InputMode::EditTitle) => {
-                note.edited = true;
-                let (first, second) = note.title.as_mut_str().split_at(app.cursor_column);
-                note.title = first.to_string() + &c.to_string() + second;
-                app.cursor_column = app.cursor_column.saturating_add(1);
+                let index = byte_index(&note.title, app.cursor_column);
+                note.title.insert(index, c);
+                // a combining mark joins the grapheme before it rather than adding one
+                app.cursor_column = column_of(&note.title, index + c.len_utf8());
             }
             (KeyCode::Enter, InputMode::Insert) => {
                 note.edited = true;
-                let mut start_lines = note
-                    .text
-                    .lines()
-                    .map(Line::raw)
-                    .collect::<Vec<ratatui::prelude::Line>>();
-                match start_lines.len() - app.cursor_row {
-                    1.. => {
-                        let mut end_lines = start_lines.split_off(app.cursor_row);
-                        end_lines.reverse();
-                        let current_line = end_lines.pop().unwrap().to_string();
-                        end_lines.reverse();
-                        // let current_line = end_lines.first().unwrap().to_string();
-                        if app.cursor_column == 0 {
-                            start_lines.push(Line::raw(""));
-                            start_lines.push(Line::raw(current_line));
-                            end_lines
-                                .iter()
-                                .for_each(|line| start_lines.push(line.to_owned()));
-                        } else if app.cursor_column < current_line.len() + 1 {
-                            let (first, second) = current_line.split_at(app.cursor_column);
-                            start_lines.push(Line::raw(first));
-                            start_lines.push(Line::raw(second));
-                            end_lines
-                                .iter()
-                                .for_each(|line| start_lines.push(line.to_owned()));
-                        } else {
-                            start_lines.push(Line::raw(""));
-                            end_lines
-                                .iter()
-                                .for_each(|line| start_lines.push(line.to_owned()));
-                        }
-
-                        note.text = Text::from(start_lines).to_string();
-                    }
-                    0 => {
-                        if start_lines
-                            .get(app.cursor_row)
-                            .is_some_and(|line| line.to_string().len() < app.cursor_column - 1)
-                        {
-                            let line = &start_lines.last().unwrap();
-                            let line = &line.to_string();
-                            let (first, second) = line.split_at(app.cursor_column);
-                            note.text = Text::from(vec![first.into(), second.into()]).to_string();
-                        } else {
-                            let line = Line::raw(" ".to_string());
-                            start_lines.push(line);
-                            note.text = Text::from(start_lines).to_string();
-                        }
-                    }
+                let mut lines = note.edit_lines();
+                let row = app.cursor_row.min(lines.len());
+                if row == lines.len() {
+                    lines.push(String::new());
                 }
+                let index = byte_index(&lines[row], app.cursor_column);
+                let rest = lines[row].split_off(index);
+                lines.insert(row + 1, rest);
+                note.set_lines(lines);
                 app.cursor_column = 0;
-                app.cursor_row = app.cursor_row.saturating_add(1);
+                app.cursor_row = row.saturating_add(1);
             }
             (KeyCode::Enter, InputMode::EditTitle) => {
                 note.mode = InputMode::Normal;
                 app.cursor_column = 0;
-                let cur_path = match app.note_list.local_list {
-                    true => PathBuf::from_str(
-                        (app.note_list.local_path.to_string_lossy() + "/notes/")
-                            .to_string()
-                            .as_str(),
-                    )
-                    .unwrap(),
-                    false => app.note_list.path.clone(),
-                };
-                note.edited = false;
-                note.is_active = false;
-                let path = if app.note_list.local_list {
-                    Some(cur_path)
+                let cur_path = app.note_list.root();
+                let extension = app.settings.extension.clone();
+                let old_title = note.old_title.take().unwrap_or_default();
+                // keep the title open for another go rather than write over a different note
+                let refused = if note.title.is_empty() {
+                    Some(Error::Untitled)
+                } else if rename::taken(&cur_path, &extension, &note, &old_title) {
+                    let path = note.file_path(&cur_path, &extension);
+                    let exists = io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        "a note with this title already exists",
+                    );
+                    Some(Error::io(&path, exists))
                 } else {
                     None
                 };
-                if self.old_title.is_none() || self.old_title.as_mut().unwrap().is_empty() {
-                    write_file(path, &mut note);
-                    app.note_list
-                        .notes
-                        .push(parse_file(note.text.clone(), Path::new(&note.title)));
-                } else {
-                    let mut old_path = path.clone().unwrap();
-                    old_path.push(note.old_title.as_ref().unwrap().to_owned() + ".md");
-                    fs::remove_file(old_path).unwrap();
-                    write_file(path,&mut note);
+                if let Some(err) = refused {
+                    note.mode = InputMode::EditTitle;
+                    note.old_title = Some(old_title);
+                    app.cursor_column = grapheme_len(&note.title);
+                    drop(note);
+                    app.report(err);
+                    return;
+                }
+                if !old_title.is_empty() && old_title != note.title {
+                    // renaming touches every note linking here, so confirm it first
+                    drop(note);
+                    let rename = Rename::plan(app, &old_title);
+                    app.note.borrow_mut().edited = true;
+                    if rename.changes.iter().all(|change| Rc::ptr_eq(&change.note, &app.note)) {
+                        rename.apply(app);
+                    } else {
+                        app.popup = Some(Popup::Rename(rename));
+                    }
+                    return;
                 }
-                note.is_active = true;
+                drop(note);
+                // through the app, so a file changed on disk is asked about rather than written over
+                let current = app.note.clone();
+                if !app.save(&current) {
+                    return;
+                }
+                if old_title.is_empty() {
+                    // the list holds the open note itself rather than a copy read back from its
+                    // file, so later edits show up there too
+                    let mut note = current.borrow_mut();
+                    note.created = note.modified;
+                    drop(note);
+                    if !app.note_list.notes.iter().any(|note| Rc::ptr_eq(note, &current)) {
+                        app.note_list.notes.push(current);
+                    }
+                }
+                app.refresh_backlinks();
             }
             (KeyCode::Left, InputMode::EditTitle) => {
                 if app.cursor_column > 0 {
@@ -226,10 +440,29 @@ impl ThisFrame for Note {
                 }
             }
             (KeyCode::Right, InputMode::EditTitle) => {
-                if app.cursor_column < self.title.len() {
+                if app.cursor_column < grapheme_len(&self.title) {
                     app.cursor_column = app.cursor_column.saturating_add(1);
                 }
             }
+            // wrapped lines move by screen row, holding the cursor's distance from the left
+            (KeyCode::Up | KeyCode::Down, InputMode::Normal | InputMode::Insert) if app.wrap => {
+                let rows = visual_rows(&note.text, Some(app.note_width));
+                let current = cursor_row(&rows, app.cursor_row, app.cursor_column);
+                let target = if key_event.code == KeyCode::Up {
+                    current.checked_sub(1)
+                } else {
+                    Some(current + 1).filter(|next| *next < rows.len())
+                };
+                let Some(target) = target else {
+                    return;
+                };
+                let lines: Vec<&str> = note.text.split('\n').collect();
+                let x = cursor_x(lines[rows[current].row], rows[current], app.cursor_column);
+                let visual = rows[target];
+                let wraps_on = rows.get(target + 1).is_some_and(|next| next.row == visual.row);
+                app.cursor_row = visual.row;
+                app.cursor_column = column_at(lines[visual.row], visual, x, wraps_on);
+            }
             (KeyCode::Up, InputMode::Normal | InputMode::Insert) => {
                 if app.cursor_row > 0 {
                     app.cursor_row = app.cursor_row.saturating_sub(1);
@@ -238,8 +471,8 @@ impl ThisFrame for Note {
             }
             (KeyCode::Down, InputMode::Normal | InputMode::Insert) => {
                 let lines: &[Line] = &Text::raw(&note.text).lines;
-                if app.cursor_row < lines.len() - 1 {
-                    let len = lines[app.cursor_row.saturating_add(1)].to_string().len();
+                if app.cursor_row + 1 < lines.len() {
+                    let len = grapheme_len(&lines[app.cursor_row.saturating_add(1)].to_string());
                     if app.cursor_column >= len {
                         app.cursor_column = len;
                         app.cursor_row = app.cursor_row.saturating_add(1);
@@ -255,34 +488,65 @@ impl ThisFrame for Note {
             }
             (KeyCode::End, InputMode::Normal | InputMode::Insert) => {
                 let lines: &[Line] = &Text::raw(&note.text).lines;
-                app.cursor_column = lines[app.cursor_row].to_string().len();
+                app.cursor_column = lines
+                    .get(app.cursor_row)
+                    .map_or(0, |line| grapheme_len(&line.to_string()));
             }
             (KeyCode::Home, InputMode::Normal | InputMode::Insert) => {
                 app.cursor_column = 0;
             }
+            (KeyCode::PageUp, InputMode::Normal | InputMode::Insert) => {
+                let page = app.note_height.max(1);
+                note.scroll = note.scroll.saturating_sub(page);
+                app.cursor_row = app.cursor_row.saturating_sub(page);
+                app.cursor_column = note.clamp_column(app.cursor_row, app.cursor_column);
+            }
+            (KeyCode::PageDown, InputMode::Normal | InputMode::Insert) => {
+                let page = app.note_height.max(1);
+                let last = note.row_count().saturating_sub(1);
+                let rows = visual_rows(&note.text, app.wrap.then_some(app.note_width)).len();
+                note.scroll = (note.scroll + page).min(rows.saturating_sub(page));
+                app.cursor_row = (app.cursor_row + page).min(last);
+                app.cursor_column = note.clamp_column(app.cursor_row, app.cursor_column);
+            }
+            (KeyCode::Char('g'), InputMode::Normal) => {
+                if pending_key == Some('g') {
+                    app.cursor_row = 0;
+                    app.cursor_column = 0;
+                } else {
+                    app.pending_key = Some('g');
+                }
+            }
+            (KeyCode::Char('G'), InputMode::Normal) => {
+                app.cursor_row = note.row_count().saturating_sub(1);
+                app.cursor_column = 0;
+            }
             (KeyCode::Char('w'), InputMode::Normal) => {
-                let line = &Text::raw(&note.text).lines[app.cursor_row].to_string();
-                if app.cursor_column < line.len() {
-                    let split = line.split_at(app.cursor_column.saturating_add(1));
-                    app.cursor_column = match split.1.find(" ") {
+                let line = note.line(app.cursor_row);
+                let graphemes = line.graphemes(true).collect::<Vec<&str>>();
+                if app.cursor_column < graphemes.len() {
+                    app.cursor_column = match graphemes[app.cursor_column + 1..]
+                        .iter()
+                        .position(|grapheme| *grapheme == " ")
+                    {
                         Some(idx) => app.cursor_column + idx + 1,
-                        None => line.len(),
+                        None => graphemes.len(),
                     }
                 }
             }
             (KeyCode::Char('b'), InputMode::Normal) => {
-                let line = &Text::raw(&note.text).lines[app.cursor_row].to_string();
+                let line = note.line(app.cursor_row);
+                let graphemes = line.graphemes(true).collect::<Vec<&str>>();
                 if app.cursor_column > 0 {
-                    let split = line.split_at(app.cursor_column.saturating_sub(1));
-                    app.cursor_column = split.0.rfind(" ").unwrap_or(0);
+                    app.cursor_column = graphemes[..(app.cursor_column - 1).min(graphemes.len())]
+                        .iter()
+                        .rposition(|grapheme| *grapheme == " ")
+                        .unwrap_or(0);
                 }
             }
             (KeyCode::Right, InputMode::Normal | InputMode::Insert) => {
-                let lines = Text::raw(self.text.clone()).lines;
-                if lines.len() <= app.cursor_row {
-                } else if app.cursor_column
-                    < lines.get(app.cursor_row).unwrap().to_string().len() - 1
-                {
+                let len = grapheme_len(note.line(app.cursor_row));
+                if app.cursor_column + 1 < len {
                     app.cursor_column += 1;
                 }
             }
@@ -291,73 +555,47 @@ impl ThisFrame for Note {
                 app.current_frame = CurrentFrame::List;
                 app.note_list.is_active = true;
             }
-            (KeyCode::Esc, InputMode::Insert) => note.mode = InputMode::Normal,
+            (KeyCode::Esc, InputMode::Insert) => {
+                note.mode = InputMode::Normal;
+                history::of(&mut app.history, &note)
+                    .commit(&note.text, (app.cursor_row, app.cursor_column));
+            }
             (KeyCode::Backspace, InputMode::Insert) => {
-                let lines = Text::raw(&note.text).lines;
+                let mut lines = note.edit_lines();
                 if app.cursor_column == 0 {
-                    if app.cursor_row == 0 || app.cursor_row == lines.len() {
-                        if app.cursor_row == lines.len() {
-                            app.cursor_row = app.cursor_row.saturating_sub(1);
-                            app.cursor_column = lines.last().unwrap().to_string().len();
+                    if app.cursor_row == 0 || app.cursor_row >= lines.len() {
+                        if app.cursor_row >= lines.len() && !lines.is_empty() {
+                            app.cursor_row = lines.len() - 1;
+                            app.cursor_column = grapheme_len(&lines[app.cursor_row]);
                         }
                     } else {
-                        let start = lines.split_at(app.cursor_row);
-                        let last = start.1.split_first().unwrap();
-                        let prev = start.0.split_last().unwrap();
-                        let new_line = prev.0.to_string() + &last.0.to_string();
-                        let mut text: Text = Text::default();
-                        app.cursor_column = prev.0.to_string().len();
-
-                        for line in prev.1.iter() {
-                            text.push_line(line.to_owned());
-                        }
-                        text.push_line(new_line);
-                        for line in last.1.iter() {
-                            text.push_line(line.to_owned());
-                        }
-                        note.text = text.to_string();
+                        let line = lines.remove(app.cursor_row);
+                        app.cursor_row -= 1;
+                        app.cursor_column = grapheme_len(&lines[app.cursor_row]);
+                        lines[app.cursor_row].push_str(&line);
+                        note.set_lines(lines);
                         note.edited = true;
-                        app.cursor_row = app.cursor_row.saturating_sub(1);
                     }
-                } else {
+                } else if let Some(line) = lines.get_mut(app.cursor_row) {
                     note.edited = true;
-                    let mut lines = Text::raw(&note.text).lines;
-                    let mut line = lines.get(app.cursor_row).unwrap().to_string();
-                    let (first, second) = line.split_at(app.cursor_column);
-                    app.cursor_column = app.cursor_column.saturating_sub(1);
-                    line = first.split_at(app.cursor_column).0.to_string() + second;
-                    lines[app.cursor_row] = Line::raw(line);
-                    let text: Text = lines.into();
-                    note.text = text.to_string();
+                    app.cursor_column = app.cursor_column.min(grapheme_len(line)).saturating_sub(1);
+                    let start = byte_index(line, app.cursor_column);
+                    let end = byte_index(line, app.cursor_column + 1);
+                    line.replace_range(start..end, "");
+                    note.set_lines(lines);
                 }
             }
             (KeyCode::Char(c), InputMode::Insert) => {
                 note.edited = true;
-                let mut lines = Text::raw(&note.text).lines;
-
-                let mut line = if app.cursor_row == lines.len().saturating_add(1) {
-                    "".to_string()
-                } else {
-                    lines
-                        .get(app.cursor_row)
-                        .unwrap_or(&Line::raw(""))
-                        .to_string()
-                };
-                if line.is_empty() {
-                    let line = c.to_string();
-                    if lines.len() <= app.cursor_row {
-                        lines.push(Line::raw(line));
-                    } else {
-                        lines[app.cursor_row] = Line::raw(line);
-                    };
-                } else {
-                    let (first, second) = line.split_at(app.cursor_column);
-                    line = first.to_string().to_owned() + &c.to_string() + second;
-                    lines[app.cursor_row] = Line::raw(line);
-                };
-                let text: Text = lines.into();
-                note.text = text.to_string();
-                app.cursor_column = app.cursor_column.saturating_add(1);
+                let mut lines = note.edit_lines();
+                while lines.len() <= app.cursor_row {
+                    lines.push(String::new());
+                }
+                let line = &mut lines[app.cursor_row];
+                let index = byte_index(line, app.cursor_column);
+                line.insert(index, c);
+                app.cursor_column = column_of(line, index + c.len_utf8());
+                note.set_lines(lines);
             }
             _ => {}
         };
@@ -365,9 +603,114 @@ impl ThisFrame for Note {
 }
 
 impl Note {
+    pub fn new() -> Note {
+        Note {
+            title: "Enter Title".to_string(),
+            text: "Text Here".to_string(),
+            links: None,
+            tags: None,
+            tasks: None,
+            key: NoteKey::default(),
+            mode: InputMode::Normal,
+            edited: false,
+            is_active: true,
+            old_title: None,
+            folder: PathBuf::new(),
+            frontmatter: Frontmatter::default(),
+            modified: None,
+            created: None,
+            disk: None,
+            changed: false,
+            scroll: 0,
+            wrap: false,
+            highlight: None,
+        }
+    }
     pub fn create_note() -> Self {
         Self::default()
     }
+    // vault relative path without the extension, unique across folders
+    pub fn id(&self) -> PathBuf {
+        self.folder.join(&self.title)
+    }
+    // put back text from the undo history
+    pub fn restore(&mut self, text: String) {
+        self.text = text;
+        self.edited = true;
+        self.refresh_tags_links();
+    }
+    // tick or untick the checkbox on a row, false when the row isn't a task
+    pub fn toggle_task(&mut self, row: usize) -> bool {
+        let Some(task) = self.tasks.iter().flatten().find(|task| task.row == row) else {
+            return false;
+        };
+        let mark = if task.done { " " } else { "x" };
+        let mut lines: Vec<String> = self.text.split('\n').map(String::from).collect();
+        // nothing but the bullet comes before the checkbox
+        let Some(open) = lines[row].find('[') else {
+            return false;
+        };
+        lines[row].replace_range(open + 1..open + 2, mark);
+        self.text = lines.join("\n");
+        self.edited = true;
+        self.refresh_tags_links();
+        true
+    }
+    // a row of the text without its line ending. rows are split on \n everywhere, so a text
+    // ending in a newline has an empty last row
+    pub fn line(&self, row: usize) -> &str {
+        let line = self.text.split('\n').nth(row).unwrap_or("");
+        line.strip_suffix('\r').unwrap_or(line)
+    }
+    pub fn row_count(&self) -> usize {
+        self.text.split('\n').count()
+    }
+    // the rows to edit, put back with set_lines
+    fn edit_lines(&self) -> Vec<String> {
+        (0..self.row_count()).map(|row| self.line(row).to_string()).collect()
+    }
+    // join edited rows with the line ending the text already uses, so a CRLF file stays CRLF
+    fn set_lines(&mut self, lines: Vec<String>) {
+        let ending = if self.text.contains("\r\n") { "\r\n" } else { "\n" };
+        self.text = lines.join(ending);
+    }
+    // keep a column within the line, for moves that land on a different row
+    pub fn clamp_column(&self, row: usize, column: usize) -> usize {
+        column.min(grapheme_len(self.line(row)))
+    }
+    // re-read tags, links and tasks from the text, frontmatter tags included
+    pub fn refresh_tags_links(&mut self) {
+        let (mut tags, links) = get_tags_links(&self.text);
+        for tag in self.frontmatter.tags.iter() {
+            let tag = Tag("#".to_string() + tag.trim_start_matches('#'));
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        self.tags = if tags.is_empty() { None } else { Some(tags) };
+        self.links = if links.is_empty() { None } else { Some(links) };
+        let tasks = get_tasks(&self.text);
+        self.tasks = if tasks.is_empty() { None } else { Some(tasks) };
+    }
+    // the line of the heading a link anchor refers to
+    pub fn heading_row(&self, anchor: &str) -> Option<usize> {
+        if anchor.trim().is_empty() {
+            return None;
+        }
+        self.text.split('\n').position(|line| {
+            heading_marker(line).is_some_and(|marker| {
+                line[marker..].trim().eq_ignore_ascii_case(anchor.trim())
+            })
+        })
+    }
+    // frontmatter followed by the body, as written to disk
+    pub fn file_contents(&self) -> String {
+        self.frontmatter.to_string() + &self.text
+    }
+    // where this note lives on disk under the given vault root
+    pub fn file_path(&self, root: &Path, extension: &str) -> PathBuf {
+        root.join(&self.folder).join(self.title.clone() + "." + extension)
+    }
     pub fn tags_to_string(&self) -> Option<String> {
         let tags = self.tags.clone()?;
         let tag_str: Vec<String> = tags.iter().map(|tag| tag.0[1..].to_owned()).collect();
@@ -381,12 +724,16 @@ impl Widget for &Note {
         Self: Sized,
     {
         let title_text = if self.is_active {
-            self.title.clone().green().bold()
+            self.title.clone().fg(theme().text).bold()
         } else {
-            self.title.clone().green()
+            self.title.clone().fg(theme().text)
         };
 
-        let title: Title = Title::from(title_text.bold());
+        let mut title = Line::from(title_text.bold());
+        if self.changed {
+            title.push_span(" (changed on disk)".fg(theme().error));
+        }
+        let title = Title::from(title);
         let mut my_border = border::ROUNDED;
         my_border.vertical_left = border::DOUBLE.vertical_left;
         my_border.horizontal_bottom = border::DOUBLE.horizontal_bottom;
@@ -395,55 +742,69 @@ impl Widget for &Note {
             .border_set(my_border)
             .style(Color::Black);
         if self.is_active {
-            block = block.set_style(Color::White);
+            block = block.set_style(theme().active);
         } else {
-            block = block.set_style(Color::Green)
+            block = block.set_style(theme().inactive)
         }
 
-        let link_regex = Regex::new(r"(?:\(.+?\))").unwrap();
-        let style_text: Vec<Span> = {
-            self.text
-                .split_inclusive(r" ")
-                .map(|token| {
-                    if token.starts_with("#") {
-                        token.magenta()
-                    } else {
-                        token.green()
-                    }
-                })
-                .collect()
-        };
+        let mut text_vec: Vec<Line> = markdown::highlight(&self.text);
 
-        let binding = self.text.clone();
-        let lines: Vec<Line> = binding.split("\n").map(Line::raw).collect::<Vec<Line>>();
-        let mut text_vec: Vec<Line> = vec![];
-        for line in lines.clone() {
-            let mut new_line: Vec<Span> = vec![];
-            let vec_line = line
-                .to_owned()
-                .to_string()
-                .split_inclusive(" ")
-                .to_owned()
-                .map(|token| token.to_string())
-                .collect::<Vec<String>>();
-
-            for token in vec_line {
-                if token.starts_with("#") {
-                    let spn = token.clone().magenta();
-                    new_line.push(spn);
-                } else {
-                    let spn = token.clone().green();
-                    new_line.push(spn);
-                }
-            }
-            text_vec.push(Line::from(new_line));
+        if let Some(regex) = self.highlight.as_deref().and_then(matcher) {
+            let matches = find_matches(&self.text, &regex);
+            let style = Style::new().bg(theme().highlight).fg(Color::Black);
+            text_vec = text_vec
+                .into_iter()
+                .enumerate()
+                .map(|(row, line)| {
+                    let ranges: Vec<(usize, usize)> = matches
+                        .iter()
+                        .filter(|found| found.row == row)
+                        .map(|found| (found.start, found.end))
+                        .collect();
+                    highlight_line(line, &ranges, style)
+                })
+                .collect();
         }
 
+        // cut each line into the screen rows it takes up
+        let width = area.width.saturating_sub(2) as usize;
+        let rows = visual_rows(&self.text, self.wrap.then_some(width));
+        let text_vec: Vec<Line> = rows
+            .iter()
+            .map(|visual| slice_line(&text_vec[visual.row], visual.start, visual.end))
+            .collect();
         let text = Text::from(text_vec);
+        let rows = text.lines.len();
 
         Paragraph::new(text)
             .left_aligned()
             .block(block)
+            .scroll((self.scroll as u16, 0))
             .render(area, buf);
+        render_scrollbar(area, buf, self.scroll, rows);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_keep_line_endings() {
+        for text in ["one\ntwo\n", "one\r\ntwo\r\n", "", "one"] {
+            let mut note = Note {
+                text: text.to_string(),
+                ..Note::create_note()
+            };
+            note.set_lines(note.edit_lines());
+            assert_eq!(note.text, text);
+        }
+        let note = Note {
+            text: "one\r\ntwo\r\n".to_string(),
+            ..Note::create_note()
+        };
+        assert_eq!(note.row_count(), 3);
+        assert_eq!(note.line(1), "two");
+        assert_eq!(note.clamp_column(0, 10), 3);
     }
 }
@@ -0,0 +1,481 @@
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::block::Title;
+use ratatui::widgets::{Block, Clear, Paragraph, Wrap};
+
+use crate::app::{App, Pending};
+use crate::error::Error;
+use crate::file_reader::read_file;
+use crate::finder::{finder_lines, Finder};
+use crate::journal::{self, Period};
+use crate::note::Note;
+use crate::rename::Rename;
+use crate::settings::{theme, Settings};
+use crate::templates::TemplateForm;
+use crate::utils::RcRc;
+
+// a modal shown over the frames, it takes every key until dismissed
+#[derive(Debug, Clone)]
+pub enum Popup {
+    Rename(Rename),
+    Error(Vec<String>),
+    // first run, asks where the vault lives and writes the config
+    Setup {
+        path: String,
+        error: Option<String>,
+        // the journal note asked for on the command line, opened once the vault exists
+        journal: Option<Period>,
+    },
+    Finder(Finder),
+    // the templates a new note can start from, a blank note sits above them
+    Templates {
+        templates: Vec<PathBuf>,
+        index: usize,
+        folder: PathBuf,
+    },
+    Template(TemplateForm),
+    // notes with edits that would be lost, asked before quitting or switching away
+    Unsaved {
+        then: Pending,
+        notes: Vec<RcRc<Note>>,
+    },
+    // the note's file changed since it was read, asks before writing over it
+    Conflict {
+        note: RcRc<Note>,
+        disk: String,
+    },
+    // swap files left by a session that didn't end cleanly, offered one at a time
+    Recover {
+        swaps: Vec<(PathBuf, PathBuf)>,
+        errors: Vec<String>,
+    },
+}
+
+impl Popup {
+    pub fn setup(journal: Option<Period>) -> Self {
+        let path = std::env::home_dir()
+            .map(|home| home.join("notes").to_string_lossy().to_string())
+            .unwrap_or_default();
+        Popup::Setup {
+            path,
+            error: None,
+            journal,
+        }
+    }
+
+    pub fn get_instructions(&self) -> Title<'_> {
+        match self {
+            Popup::Rename(_) => Title::from(Line::from(vec![
+                " Apply ".into(),
+                "<y>".blue().bold(),
+                " Cancel ".into(),
+                "<n>".blue().bold(),
+            ])),
+            Popup::Error(_) => Title::from(Line::from(vec![
+                " Dismiss ".into(),
+                "<any key>".blue().bold(),
+            ])),
+            Popup::Setup { .. } => Title::from(Line::from(vec![
+                " Create ".into(),
+                "<return>".blue().bold(),
+                " Quit ".into(),
+                "<esc>".blue().bold(),
+            ])),
+            Popup::Templates { .. } => Title::from(Line::from(vec![
+                " Use ".into(),
+                "<return>".blue().bold(),
+                " Move ".into(),
+                "<UP/DOWN>".blue().bold(),
+                " Cancel ".into(),
+                "<esc>".blue().bold(),
+            ])),
+            Popup::Template(_) => Title::from(Line::from(vec![
+                " Next ".into(),
+                "<return>".blue().bold(),
+                " Cancel ".into(),
+                "<esc>".blue().bold(),
+            ])),
+            Popup::Unsaved { .. } => Title::from(Line::from(vec![
+                " Save ".into(),
+                "<s>".blue().bold(),
+                " Discard ".into(),
+                "<d>".blue().bold(),
+                " Cancel ".into(),
+                "<esc>".blue().bold(),
+            ])),
+            Popup::Conflict { .. } => Title::from(Line::from(vec![
+                " Overwrite ".into(),
+                "<o>".blue().bold(),
+                " Reload ".into(),
+                "<r>".blue().bold(),
+                " Merge ".into(),
+                "<m>".blue().bold(),
+                " Cancel ".into(),
+                "<esc>".blue().bold(),
+            ])),
+            Popup::Recover { .. } => Title::from(Line::from(vec![
+                " Recover ".into(),
+                "<r>".blue().bold(),
+                " Delete ".into(),
+                "<d>".blue().bold(),
+                " Skip ".into(),
+                "<esc>".blue().bold(),
+            ])),
+            Popup::Finder(_) => Title::from(Line::from(vec![
+                " Open ".into(),
+                "<return>".blue().bold(),
+                " Move ".into(),
+                "<UP/DOWN>".blue().bold(),
+                " Close ".into(),
+                "<esc>".blue().bold(),
+            ])),
+        }
+    }
+
+    pub fn handle_key_event(&self, app: &mut App, key_event: KeyEvent) {
+        match (self, key_event.code) {
+            (Popup::Rename(rename), KeyCode::Char('y') | KeyCode::Enter) => {
+                app.popup = None;
+                rename.clone().apply(app);
+            }
+            (Popup::Rename(rename), KeyCode::Char('n') | KeyCode::Esc) => {
+                app.popup = None;
+                rename.clone().cancel();
+            }
+            (Popup::Error(_), _) => app.popup = None,
+            (Popup::Setup { path, journal, .. }, KeyCode::Enter) => {
+                let expanded = match (path.strip_prefix("~/"), std::env::home_dir()) {
+                    (Some(rest), Some(home)) => home.join(rest),
+                    _ => path.into(),
+                };
+                match Settings::create(expanded) {
+                    Ok(settings) => {
+                        app.popup = None;
+                        app.load_settings(settings);
+                        if let Some(period) = journal {
+                            app.open_journal(*period, journal::today());
+                        }
+                    }
+                    Err(err) => {
+                        app.popup = Some(Popup::Setup {
+                            path: path.clone(),
+                            error: Some(err.to_string()),
+                            journal: *journal,
+                        })
+                    }
+                }
+            }
+            (Popup::Setup { path, journal, .. }, KeyCode::Backspace) => {
+                let mut path = path.clone();
+                path.pop();
+                app.popup = Some(Popup::Setup {
+                    path,
+                    error: None,
+                    journal: *journal,
+                });
+            }
+            (Popup::Setup { path, journal, .. }, KeyCode::Char(c)) => {
+                app.popup = Some(Popup::Setup {
+                    path: path.clone() + &c.to_string(),
+                    error: None,
+                    journal: *journal,
+                });
+            }
+            (Popup::Setup { .. }, KeyCode::Esc) => app.exit(),
+            (Popup::Finder(finder), KeyCode::Enter) => {
+                app.popup = None;
+                let Some(hit) = finder.hits().into_iter().nth(finder.index) else {
+                    return;
+                };
+                app.open(hit.note, (0, 0));
+            }
+            (Popup::Finder(_), KeyCode::Esc) => app.popup = None,
+            (Popup::Finder(finder), KeyCode::Up) => {
+                app.popup = Some(Popup::Finder(finder.step(false)))
+            }
+            (Popup::Finder(finder), KeyCode::Down) => {
+                app.popup = Some(Popup::Finder(finder.step(true)))
+            }
+            (Popup::Finder(finder), KeyCode::Backspace) => {
+                app.popup = Some(Popup::Finder(finder.pop()))
+            }
+            (Popup::Finder(finder), KeyCode::Char(c)) => {
+                app.popup = Some(Popup::Finder(finder.push(c)))
+            }
+            (Popup::Templates { templates, index, folder }, KeyCode::Enter) => {
+                app.popup = None;
+                let Some(path) = index.checked_sub(1).and_then(|i| templates.get(i)) else {
+                    app.new_note(folder.clone());
+                    return;
+                };
+                match read_file(path) {
+                    Ok(text) => {
+                        let name = template_name(path);
+                        let form = TemplateForm::new(name, text, folder.clone());
+                        app.popup = Some(Popup::Template(form));
+                    }
+                    Err(err) => app.report(err),
+                }
+            }
+            (Popup::Templates { templates, index, folder }, KeyCode::Up | KeyCode::Down) => {
+                // the blank note is one more row than there are templates
+                let count = templates.len() + 1;
+                let index = if key_event.code == KeyCode::Down {
+                    (index + 1) % count
+                } else {
+                    (index + count - 1) % count
+                };
+                app.popup = Some(Popup::Templates {
+                    templates: templates.clone(),
+                    index,
+                    folder: folder.clone(),
+                });
+            }
+            (Popup::Templates { .. } | Popup::Template(_), KeyCode::Esc) => app.popup = None,
+            (Popup::Template(form), KeyCode::Enter) => {
+                let form = form.answer();
+                if form.is_done() {
+                    app.popup = None;
+                    app.create_from_template(&form);
+                } else {
+                    app.popup = Some(Popup::Template(form));
+                }
+            }
+            (Popup::Template(form), KeyCode::Backspace) => {
+                app.popup = Some(Popup::Template(form.pop()))
+            }
+            (Popup::Template(form), KeyCode::Char(c)) => {
+                app.popup = Some(Popup::Template(form.push(c)))
+            }
+            (Popup::Unsaved { then, notes }, KeyCode::Char('s')) => {
+                app.popup = None;
+                // stay put if any of them couldn't be written, the error says why
+                if notes.iter().all(|note| app.save(note)) {
+                    app.carry_on(then.clone());
+                }
+            }
+            (Popup::Unsaved { then, notes }, KeyCode::Char('d')) => {
+                app.popup = None;
+                for note in notes {
+                    app.discard(note);
+                }
+                app.carry_on(then.clone());
+            }
+            (Popup::Unsaved { .. }, KeyCode::Esc) => app.popup = None,
+            (Popup::Conflict { note, .. }, KeyCode::Char('o')) => {
+                app.popup = None;
+                app.overwrite(note);
+            }
+            (Popup::Conflict { note, .. }, KeyCode::Char('r')) => {
+                app.popup = None;
+                app.discard(note);
+            }
+            (Popup::Conflict { note, disk }, KeyCode::Char('m')) => {
+                app.popup = None;
+                app.merge(note, disk.clone());
+            }
+            (Popup::Conflict { .. }, KeyCode::Esc) => app.popup = None,
+            (Popup::Recover { swaps, errors }, KeyCode::Char('r' | 'd') | KeyCode::Esc) => {
+                let Some((swap, path)) = swaps.first() else {
+                    return;
+                };
+                let done = match key_event.code {
+                    KeyCode::Char('r') => app.recover(swap, path),
+                    KeyCode::Char('d') => {
+                        std::fs::remove_file(swap).map_err(|err| Error::io(swap, err))
+                    }
+                    _ => Ok(()),
+                };
+                // failures wait with the notes that didn't load until every swap file is seen
+                let mut errors = errors.clone();
+                if let Err(err) = done {
+                    errors.push(err.to_string());
+                }
+                app.popup = if swaps.len() > 1 {
+                    Some(Popup::Recover {
+                        swaps: swaps[1..].to_vec(),
+                        errors,
+                    })
+                } else if errors.is_empty() {
+                    None
+                } else {
+                    Some(Popup::Error(errors))
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+// a template's file name, which is how the picker shows it
+fn template_name(path: &Path) -> String {
+    path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string())
+}
+
+impl Widget for &Popup {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let [_, area, _] = Layout::vertical(Constraint::from_percentages([20, 60, 20])).areas(area);
+        let [_, area, _] =
+            Layout::horizontal(Constraint::from_percentages([20, 60, 20])).areas(area);
+        let mut colour = theme().active;
+        let (title, lines): (String, Vec<Line>) = match self {
+            Popup::Error(errors) => {
+                colour = theme().error;
+                (
+                    " Error ".to_string(),
+                    errors.iter().map(|err| Line::raw(err.clone())).collect(),
+                )
+            }
+            Popup::Setup { path, error, .. } => {
+                let mut lines = vec![
+                    Line::raw("No config was found. Where should your vault live?"),
+                    Line::raw(""),
+                    Line::from(vec!["> ".into(), path.clone().fg(theme().text), "█".into()]),
+                ];
+                if let Some(error) = error {
+                    lines.push(Line::raw(""));
+                    lines.push(Line::raw(error.clone()).fg(theme().error));
+                }
+                (" Welcome to noter ".to_string(), lines)
+            }
+            Popup::Finder(finder) => (
+                " Find Note ".to_string(),
+                finder_lines(finder, area.height.saturating_sub(2) as usize),
+            ),
+            Popup::Templates { templates, index, .. } => {
+                let names = std::iter::once("Blank note".to_string())
+                    .chain(templates.iter().map(|path| template_name(path)));
+                let lines = names
+                    .enumerate()
+                    .map(|(count, name)| {
+                        if count == *index {
+                            Line::from(vec!["▶ ".into(), name.fg(theme().selected)])
+                        } else {
+                            Line::from(vec!["  ".into(), name.fg(theme().text)])
+                        }
+                    })
+                    .collect();
+                (" New Note ".to_string(), lines)
+            }
+            Popup::Template(form) => {
+                let mut lines: Vec<Line> = form
+                    .prompts
+                    .iter()
+                    .zip(form.answers.iter())
+                    .map(|(prompt, answer)| {
+                        let prompt = format!("{}: ", prompt).dim();
+                        Line::from(vec![prompt, answer.clone().fg(theme().text)])
+                    })
+                    .collect();
+                if let Some(prompt) = form.prompts.get(form.answers.len()) {
+                    lines.push(Line::from(format!("{}:", prompt).bold()));
+                    lines.push(Line::from(vec![
+                        "> ".into(),
+                        form.input.clone().fg(theme().text),
+                        "█".into(),
+                    ]));
+                }
+                if let Some(error) = &form.error {
+                    lines.push(Line::raw(""));
+                    lines.push(Line::raw(error.clone()).fg(theme().error));
+                }
+                (format!(" New Note from {} ", form.name), lines)
+            }
+            Popup::Unsaved { notes, .. } => {
+                let mut lines = vec![Line::raw("These notes have unsaved edits:"), Line::raw("")];
+                for note in notes {
+                    let note = note.borrow();
+                    let name = if note.title.is_empty() {
+                        "Untitled".to_string()
+                    } else {
+                        note.id().to_string_lossy().to_string()
+                    };
+                    lines.push(Line::from(vec!["  ".into(), name.fg(theme().text)]));
+                }
+                (" Unsaved Changes ".to_string(), lines)
+            }
+            Popup::Conflict { note, disk } => {
+                let note = note.borrow();
+                let mut lines = vec![
+                    Line::raw(format!(
+                        "{} was changed by something else since it was read.",
+                        note.id().to_string_lossy()
+                    )),
+                    Line::raw("Lines marked + are on disk, - only here:"),
+                    Line::raw(""),
+                ];
+                let patch = diffy::create_patch(&note.file_contents(), disk).to_string();
+                // the first two lines name the sides, which are unnamed here
+                for line in patch.lines().skip(2) {
+                    let line = line.to_string();
+                    lines.push(match line.chars().next() {
+                        Some('+') => Line::raw(line).fg(theme().done),
+                        Some('-') => Line::raw(line).fg(theme().error),
+                        Some('@') => Line::raw(line).dim(),
+                        _ => Line::raw(line).fg(theme().text),
+                    });
+                }
+                (" Changed on Disk ".to_string(), lines)
+            }
+            Popup::Recover { swaps, .. } => {
+                let (swap, path) = &swaps[0];
+                let path = path.to_string_lossy().to_string();
+                let lines = vec![
+                    Line::raw("Unsaved edits were left behind for"),
+                    Line::raw(""),
+                    Line::from(vec!["  ".into(), path.fg(theme().text)]),
+                    Line::raw(""),
+                    Line::raw(format!("kept in {}", swap.to_string_lossy())).dim(),
+                ];
+                let title = if swaps.len() > 1 {
+                    format!(" Recover ({} left) ", swaps.len())
+                } else {
+                    " Recover ".to_string()
+                };
+                (title, lines)
+            }
+            Popup::Rename(rename) => {
+                let total: usize = rename.changes.iter().map(|change| change.count).sum();
+                let mut lines = vec![
+                    Line::raw(format!(
+                        "{} links in {} notes will be updated:",
+                        total,
+                        rename.changes.len()
+                    )),
+                    Line::raw(""),
+                ];
+                for change in rename.changes.iter() {
+                    let note = change.note.borrow();
+                    lines.push(Line::from(vec![
+                        "  ".into(),
+                        note.id().to_string_lossy().to_string().fg(theme().text),
+                        format!(" ({})", change.count).dim(),
+                    ]));
+                }
+                (
+                    format!(" Rename {} to {} ", rename.old_title, rename.new_title()),
+                    lines,
+                )
+            }
+        };
+        let block = Block::bordered()
+            .title(Title::from(title.bold()).alignment(Alignment::Center))
+            .title(
+                self.get_instructions()
+                    .alignment(Alignment::Center)
+                    .position(ratatui::widgets::block::Position::Bottom),
+            )
+            .border_set(symbols::border::ROUNDED)
+            .style(colour);
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,390 @@
+use std::cmp::Ordering;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::note::Note;
+
+// a parsed list filter such as `tag:work AND (tag:urgent OR tag:blocked) AND NOT tag:done`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Tag(String),
+    Title(String),
+    Body(String),
+    // a bare word, a tag or part of an alias as the old tag search took it
+    Term(String),
+    Date {
+        field: DateField,
+        op: Op,
+        day: NaiveDate,
+    },
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+    Modified,
+    Created,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Less,
+    LessEq,
+    Eq,
+    GreaterEq,
+    Greater,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+// parse a search box, terms side by side join with AND when `all` is set and OR otherwise
+pub fn parse(input: &str, all: bool) -> Result<Option<Query>, String> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        all,
+    };
+    let query = parser.or()?;
+    match parser.peek() {
+        None => Ok(Some(query)),
+        Some(Token::Close) => Err("unexpected `)`".to_string()),
+        Some(token) => Err(format!("unexpected {}", describe(token))),
+    }
+}
+
+impl Query {
+    pub fn matches(&self, note: &Note) -> bool {
+        let contains = |haystack: &str, needle: &str| {
+            haystack.to_lowercase().contains(&needle.to_lowercase())
+        };
+        match self {
+            Query::Tag(tag) => has_tag(note, tag),
+            Query::Title(text) => contains(&note.title, text),
+            Query::Body(text) => contains(&note.text, text),
+            Query::Term(term) => {
+                has_tag(note, term)
+                    || note
+                        .frontmatter
+                        .aliases
+                        .iter()
+                        .any(|alias| contains(alias, term))
+            }
+            Query::Date { field, op, day } => {
+                let time = match field {
+                    DateField::Modified => note.modified,
+                    DateField::Created => note.created,
+                };
+                time.map(to_day).is_some_and(|note_day| {
+                    let ordering = note_day.cmp(day);
+                    match op {
+                        Op::Less => ordering == Ordering::Less,
+                        Op::LessEq => ordering != Ordering::Greater,
+                        Op::Eq => ordering == Ordering::Equal,
+                        Op::GreaterEq => ordering != Ordering::Less,
+                        Op::Greater => ordering == Ordering::Greater,
+                    }
+                })
+            }
+            Query::Not(query) => !query.matches(note),
+            Query::And(left, right) => left.matches(note) && right.matches(note),
+            Query::Or(left, right) => left.matches(note) || right.matches(note),
+        }
+    }
+}
+
+// nested tags count, tag:project finds #project/noter/ui
+fn has_tag(note: &Note, tag: &str) -> bool {
+    note.tags.iter().flatten().any(|found| found.is_under(tag))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    all: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut query = self.and()?;
+        loop {
+            match self.peek() {
+                Some(Token::Or) => {
+                    self.next();
+                    self.needs("OR")?;
+                }
+                // terms written side by side, the and level takes them when they join with AND
+                Some(Token::Word(_) | Token::Not | Token::Open) => {}
+                _ => return Ok(query),
+            }
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut query = self.not()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                    self.needs("AND")?;
+                    query = Query::And(Box::new(query), Box::new(self.not()?));
+                }
+                // terms written side by side
+                Some(Token::Word(_) | Token::Not | Token::Open) if self.all => {
+                    query = Query::And(Box::new(query), Box::new(self.not()?));
+                }
+                _ => return Ok(query),
+            }
+        }
+    }
+
+    // an operator with nothing to take as its right hand side
+    fn needs(&self, operator: &str) -> Result<(), String> {
+        match self.peek() {
+            None | Some(Token::Close | Token::And | Token::Or) => {
+                Err(format!("`{}` needs a term after it", operator))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn not(&mut self) -> Result<Query, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            self.needs("NOT")?;
+            return Ok(Query::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Query, String> {
+        match self.next() {
+            Some(Token::Open) => {
+                if self.peek() == Some(&Token::Close) {
+                    return Err("empty `()`".to_string());
+                }
+                let query = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err("unclosed `(`".to_string()),
+                }
+            }
+            Some(Token::Word(word)) => term(&word),
+            Some(token) => Err(format!("unexpected {}", describe(&token))),
+            None => Err("unexpected end of query".to_string()),
+        }
+    }
+}
+
+fn term(word: &str) -> Result<Query, String> {
+    for (name, field) in [
+        ("modified", DateField::Modified),
+        ("created", DateField::Created),
+    ] {
+        // a bare `created` is a word to look for, a date needs an operator after it
+        if let Some(rest) = word.strip_prefix(name) {
+            if rest.starts_with([':', '<', '>', '=']) {
+                return date(name, field, rest.trim_start_matches(':'));
+            }
+        }
+    }
+    let Some((field, value)) = word.split_once(':') else {
+        return Ok(Query::Term(word.trim_start_matches('#').to_string()));
+    };
+    if value.is_empty() {
+        return Err(format!("`{}:` needs a value", field));
+    }
+    match field {
+        "tag" => Ok(Query::Tag(value.trim_start_matches('#').to_string())),
+        "title" => Ok(Query::Title(value.to_string())),
+        "body" => Ok(Query::Body(value.to_string())),
+        _ => Err(format!("unknown field `{}:`", field)),
+    }
+}
+
+// `>=2024-01-31` and friends, a plain date means that day
+fn date(name: &str, field: DateField, rest: &str) -> Result<Query, String> {
+    let (op, value) = [
+        ("<=", Op::LessEq),
+        (">=", Op::GreaterEq),
+        ("<", Op::Less),
+        (">", Op::Greater),
+        ("=", Op::Eq),
+    ]
+    .into_iter()
+    .find_map(|(symbol, op)| rest.strip_prefix(symbol).map(|value| (op, value)))
+    .unwrap_or((Op::Eq, rest));
+    let day = parse_day(value)
+        .ok_or_else(|| format!("`{}` needs a date like {}>=2024-01-31", name, name))?;
+    Ok(Query::Date { field, op, day })
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                // a word runs to whitespace or a bracket, quotes keep spaces in
+                let mut word = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if !quoted && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' {
+                        quoted = !quoted;
+                    } else {
+                        word.push(c);
+                    }
+                }
+                if quoted {
+                    return Err("unclosed `\"`".to_string());
+                }
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "`(`".to_string(),
+        Token::Close => "`)`".to_string(),
+        Token::And => "`AND`".to_string(),
+        Token::Or => "`OR`".to_string(),
+        Token::Not => "`NOT`".to_string(),
+        Token::Word(word) => format!("`{}`", word),
+    }
+}
+
+// a YYYY-MM-DD date, only ones that exist on the calendar
+pub fn parse_day(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+// the local date a file time falls on, so notes made just after midnight count as that day
+pub fn to_day(time: SystemTime) -> NaiveDate {
+    DateTime::<Local>::from(time).date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str) -> Box<Query> {
+        Box::new(Query::Tag(name.to_string()))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let query = parse("tag:a OR tag:b AND NOT tag:c", false).unwrap();
+        let right = Query::And(tag("b"), Box::new(Query::Not(tag("c"))));
+        assert_eq!(query, Some(Query::Or(tag("a"), Box::new(right))));
+    }
+
+    #[test]
+    fn brackets_and_side_by_side_terms() {
+        let query = parse("(tag:#a tag:b) title:\"two words\"", true).unwrap();
+        let title = Box::new(Query::Title("two words".to_string()));
+        let expected = Query::And(Box::new(Query::And(tag("a"), tag("b"))), title);
+        assert_eq!(query, Some(expected));
+        let query = parse("work home", false).unwrap();
+        let term = |word: &str| Box::new(Query::Term(word.to_string()));
+        assert_eq!(query, Some(Query::Or(term("work"), term("home"))));
+        assert_eq!(parse("  ", true), Ok(None));
+    }
+
+    #[test]
+    fn side_by_side_or_binds_looser_than_and() {
+        let query = parse("tag:a tag:b AND tag:c", false).unwrap();
+        let right = Query::And(tag("b"), tag("c"));
+        assert_eq!(query, Some(Query::Or(tag("a"), Box::new(right))));
+    }
+
+    #[test]
+    fn date_words_without_a_date_are_terms() {
+        let term = |word: &str| Box::new(Query::Term(word.to_string()));
+        let query = parse("created modified", false).unwrap();
+        assert_eq!(query, Some(Query::Or(term("created"), term("modified"))));
+        assert!(parse("created:", true).is_err());
+    }
+
+    #[test]
+    fn dates() {
+        let query = parse("modified>=2024-01-31 created:2024-02-29", true).unwrap();
+        let modified = Query::Date {
+            field: DateField::Modified,
+            op: Op::GreaterEq,
+            day: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+        };
+        let created = Query::Date {
+            field: DateField::Created,
+            op: Op::Eq,
+            day: NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+        };
+        assert_eq!(query, Some(Query::And(Box::new(modified), Box::new(created))));
+        assert!(parse("created:2023-02-29", true).is_err());
+        assert!(parse("modified<yesterday", true).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        for input in [
+            "tag:a AND",
+            "NOT",
+            "(tag:a",
+            "tag:a)",
+            "()",
+            "title:\"open",
+            "tag:",
+            "colour:red",
+            "OR tag:a",
+        ] {
+            assert!(parse(input, true).is_err(), "{}", input);
+        }
+    }
+}
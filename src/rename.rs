@@ -0,0 +1,238 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::app::App;
+use crate::error::{Error, Result};
+use crate::file_reader::{changed_on_disk, get_links, write_file};
+use crate::note::{Link, Note};
+use crate::popup::Popup;
+use crate::swap;
+use crate::utils::RcRc;
+
+#[derive(Debug, Clone)]
+pub struct RenameChange {
+    pub note: RcRc<Note>,
+    // the note text with every link to the renamed note rewritten
+    pub text: String,
+    pub count: usize,
+}
+
+// a title change for the open note along with the link rewrites it needs
+#[derive(Debug, Clone)]
+pub struct Rename {
+    pub note: RcRc<Note>,
+    pub old_title: String,
+    // whether the note had unsaved edits before the rename, cancelling puts it back
+    pub edited: bool,
+    pub changes: Vec<RenameChange>,
+}
+
+impl Rename {
+    // the open note already carries its new title, old_title is what links still use
+    pub fn plan(app: &App, old_title: &str) -> Rename {
+        let note = app.note.clone();
+        let (folder, new_title, edited) = {
+            let note = note.borrow();
+            (note.folder.clone(), note.title.clone(), note.edited)
+        };
+        let old_id = folder.join(old_title);
+        // another note sharing the old title means bare [[Old]] links may not be ours
+        let shared_title = app.note_list.notes.iter().any(|other| {
+            !Rc::ptr_eq(other, &note) && other.borrow().title.eq_ignore_ascii_case(old_title)
+        });
+        let mut candidates = app.note_list.notes.clone();
+        if !candidates.iter().any(|other| Rc::ptr_eq(other, &note)) {
+            candidates.push(note.clone());
+        }
+        let mut changes = vec![];
+        for candidate in candidates {
+            let (text, count) = rewrite_links(
+                &candidate.borrow().text,
+                &old_id,
+                old_title,
+                &new_title,
+                shared_title,
+            );
+            if count > 0 {
+                changes.push(RenameChange {
+                    note: candidate.clone(),
+                    text,
+                    count,
+                });
+            }
+        }
+        Rename {
+            note,
+            old_title: old_title.to_string(),
+            edited,
+            changes,
+        }
+    }
+
+    pub fn apply(self, app: &mut App) {
+        let root = app.note_list.root();
+        let extension = app.settings.extension.clone();
+        match self.conflict(&root, &extension) {
+            Ok(None) => {}
+            Ok(Some((note, disk))) => {
+                self.cancel();
+                app.popup = Some(Popup::Conflict { note, disk });
+                return;
+            }
+            Err(err) => {
+                self.cancel();
+                app.report(err);
+                return;
+            }
+        }
+        let mut errors = vec![];
+        for change in self.changes {
+            let mut note = change.note.borrow_mut();
+            note.text = change.text;
+            note.refresh_tags_links();
+            if !Rc::ptr_eq(&change.note, &self.note) {
+                // any unsaved edits went to disk along with the rewritten links
+                match write_file(&root, &extension, &mut note) {
+                    Ok(()) => {
+                        note.edited = false;
+                        errors.extend(swap::remove(&root, &extension, &note).err());
+                    }
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        let mut note = self.note.borrow_mut();
+        let old_path = root
+            .join(&note.folder)
+            .join(self.old_title.clone() + "." + &extension);
+        // move the file before writing it, so a change of case on a case-insensitive filesystem
+        // doesn't write the new name and then delete it as the old one
+        let new_path = note.file_path(&root, &extension);
+        match fs::rename(&old_path, &new_path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                errors.push(Error::io(&old_path, err))
+            }
+            _ => {}
+        }
+        match write_file(&root, &extension, &mut note) {
+            Ok(()) => {
+                note.edited = false;
+                // the edits the old swap file kept are in the new file now
+                let old_swap = swap::titled(&root, &extension, &note.folder, &self.old_title);
+                errors.extend(swap::remove_at(&old_swap).err());
+                errors.extend(swap::remove(&root, &extension, &note).err());
+            }
+            Err(err) => errors.push(err),
+        }
+        note.refresh_tags_links();
+        drop(note);
+        app.refresh_backlinks();
+        app.report_all(errors);
+    }
+
+    // the first file to be written that changed outside noter since it was read. the rename is
+    // left undone until that's settled rather than write over it
+    fn conflict(&self, root: &Path, extension: &str) -> Result<Option<(RcRc<Note>, String)>> {
+        for change in self.changes.iter() {
+            if Rc::ptr_eq(&change.note, &self.note) {
+                continue;
+            }
+            if let Some(disk) = changed_on_disk(root, extension, &change.note.borrow())? {
+                return Ok(Some((change.note.clone(), disk)));
+            }
+        }
+        // the open note's file is still under its old title
+        let mut old = self.note.borrow().clone();
+        old.title = self.old_title.clone();
+        Ok(changed_on_disk(root, extension, &old)?.map(|disk| (self.note.clone(), disk)))
+    }
+
+    pub fn cancel(self) {
+        let mut note = self.note.borrow_mut();
+        note.title = self.old_title;
+        note.edited = self.edited;
+    }
+
+    pub fn new_title(&self) -> String {
+        self.note.borrow().title.clone()
+    }
+}
+
+// whether the note's file under its new title would be some other note's. a change of case alone
+// finds the note's own file on a case-insensitive filesystem, so then the exact name is looked for
+pub fn taken(root: &Path, extension: &str, note: &Note, old_title: &str) -> bool {
+    let path = note.file_path(root, extension);
+    if old_title == note.title || !path.exists() {
+        return false;
+    }
+    if old_title.is_empty() || old_title.to_lowercase() != note.title.to_lowercase() {
+        return true;
+    }
+    let (Some(folder), Some(name)) = (path.parent(), path.file_name()) else {
+        return true;
+    };
+    fs::read_dir(folder).map_or(true, |entries| {
+        entries.flatten().any(|entry| entry.file_name() == name)
+    })
+}
+
+// rewrite [[Old]] and [..](Old.md) links to the new title, returning the text and how many changed
+fn rewrite_links(
+    text: &str,
+    old_id: &Path,
+    old_title: &str,
+    new_title: &str,
+    shared_title: bool,
+) -> (String, usize) {
+    let mut out = String::new();
+    let mut count = 0;
+    for line in text.split_inclusive('\n') {
+        let mut last = 0;
+        for link in get_links(line) {
+            if !points_at(&link, old_id, old_title, shared_title) {
+                continue;
+            }
+            out.push_str(&line[last..link.start]);
+            out.push_str(&retarget(&line[link.start..link.end], &link, new_title));
+            last = link.end;
+            count += 1;
+        }
+        out.push_str(&line[last..]);
+    }
+    (out, count)
+}
+
+fn points_at(link: &Link, old_id: &Path, old_title: &str, shared_title: bool) -> bool {
+    if link.target.is_empty() || link.is_external() {
+        return false;
+    }
+    let target = link.target.trim_start_matches('/');
+    if target.eq_ignore_ascii_case(&old_id.to_string_lossy()) {
+        return true;
+    }
+    let name = target.rsplit('/').next().unwrap_or(target);
+    !shared_title && name.eq_ignore_ascii_case(old_title)
+}
+
+// swap the last path component of a link's target, leaving anchor, alias and folders as written
+fn retarget(raw: &str, link: &Link, new_title: &str) -> String {
+    let replace_name = |target: &str, name: &str| match target.rsplit_once('/') {
+        Some((folder, _)) => folder.to_string() + "/" + name,
+        None => name.to_string(),
+    };
+    if link.wiki {
+        let end = raw[2..].find(['#', '|', ']']).map_or(raw.len(), |end| end + 2);
+        return "[[".to_string() + &replace_name(raw[2..end].trim(), new_title) + &raw[end..];
+    }
+    let Some(open) = raw.find("](") else {
+        return raw.to_string();
+    };
+    let url = &raw[open + 2..raw.len() - 1];
+    let path_end = url.find('#').unwrap_or(url.len());
+    let path = &url[..path_end];
+    let extension = if path.ends_with(".md") { ".md" } else { "" };
+    let name = new_title.replace(' ', "%20") + extension;
+    raw[..open + 2].to_string() + &replace_name(path, &name) + &url[path_end..] + ")"
+}
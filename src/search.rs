@@ -0,0 +1,118 @@
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use regex::{Regex, RegexBuilder};
+
+use crate::note::Note;
+use crate::utils::RcRc;
+
+// a hit in note text, columns are a byte range of the line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match {
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub note: RcRc<Note>,
+    pub score: usize,
+    // the line of the first body match, with the match's byte range in it
+    pub snippet: Option<(String, usize, usize)>,
+}
+
+// a case-insensitive matcher for the query taken literally, None for a blank query
+pub fn matcher(query: &str) -> Option<Regex> {
+    if query.trim().is_empty() {
+        return None;
+    }
+    RegexBuilder::new(&regex::escape(query.trim()))
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+pub fn find_matches(text: &str, regex: &Regex) -> Vec<Match> {
+    text.split('\n')
+        .enumerate()
+        .flat_map(|(row, line)| {
+            regex.find_iter(line).map(move |found| Match {
+                row,
+                start: found.start(),
+                end: found.end(),
+            })
+        })
+        .collect()
+}
+
+// notes whose title or body contains the query, best first
+pub fn search(notes: &[RcRc<Note>], query: &str) -> Vec<SearchResult> {
+    let Some(regex) = matcher(query) else {
+        return vec![];
+    };
+    let mut results: Vec<SearchResult> = notes
+        .iter()
+        .filter_map(|note| {
+            let borrowed = note.borrow();
+            let matches = find_matches(&borrowed.text, &regex);
+            // a title hit outranks any number of body hits, a whole title match most of all
+            let title = match regex.find(&borrowed.title) {
+                Some(found) if found.len() == borrowed.title.len() => 2000,
+                Some(found) if found.start() == 0 => 1500,
+                Some(_) => 1000,
+                None => 0,
+            };
+            let score = title + matches.len().min(999);
+            if score == 0 {
+                return None;
+            }
+            let snippet = matches.first().map(|found| {
+                let line = borrowed.text.split('\n').nth(found.row).unwrap_or("");
+                (line.to_string(), found.start, found.end)
+            });
+            Some(SearchResult {
+                note: note.clone(),
+                score,
+                snippet,
+            })
+        })
+        .collect();
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.note.borrow().title.cmp(&b.note.borrow().title))
+    });
+    results
+}
+
+// restyle the byte ranges of a styled line, splitting spans where a range starts or ends
+pub fn highlight_line<'a>(line: Line<'a>, ranges: &[(usize, usize)], style: Style) -> Line<'a> {
+    if ranges.is_empty() {
+        return line;
+    }
+    let mut spans = vec![];
+    let mut offset = 0;
+    for span in line.spans.iter() {
+        let content = span.content.to_string();
+        let end = offset + content.len();
+        let mut cuts = vec![0, content.len()];
+        for (start, stop) in ranges {
+            for cut in [*start, *stop] {
+                if cut > offset && cut < end {
+                    cuts.push(cut - offset);
+                }
+            }
+        }
+        cuts.sort();
+        cuts.dedup();
+        for pair in cuts.windows(2) {
+            let piece = &content[pair[0]..pair[1]];
+            let at = offset + pair[0];
+            let hit = ranges.iter().any(|(start, stop)| *start <= at && at < *stop);
+            let piece_style = if hit { span.style.patch(style) } else { span.style };
+            spans.push(Span::styled(piece.to_string(), piece_style));
+        }
+        offset = end;
+    }
+    Line::from(spans).style(line.style)
+}
@@ -0,0 +1,368 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use config::Config;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::code;
+use crate::error::{Error, Result};
+use crate::journal::Period;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    #[default]
+    Title,
+    Modified,
+    Created,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Theme {
+    pub text: Color,
+    pub tag: Color,
+    pub link: Color,
+    pub folder: Color,
+    pub selected: Color,
+    pub active: Color,
+    pub inactive: Color,
+    pub error: Color,
+    // background of search matches
+    pub highlight: Color,
+    // markdown in the note view
+    pub heading: Color,
+    pub code: Color,
+    pub quote: Color,
+    // list bullets, quote bars, emphasis and heading markers
+    pub marker: Color,
+    pub task: Color,
+    pub done: Color,
+    // syntect colour scheme for fenced code with a language
+    pub syntax: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            text: Color::Green,
+            tag: Color::Magenta,
+            link: Color::Cyan,
+            folder: Color::Yellow,
+            selected: Color::Blue,
+            active: Color::White,
+            inactive: Color::Green,
+            error: Color::Red,
+            highlight: Color::Yellow,
+            heading: Color::LightCyan,
+            code: Color::LightYellow,
+            quote: Color::Gray,
+            marker: Color::DarkGray,
+            task: Color::LightRed,
+            done: Color::DarkGray,
+            syntax: "base16-ocean.dark".to_string(),
+        }
+    }
+}
+
+// daily, weekly and monthly notes, titled by strftime formats
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Journal {
+    // folder under the vault, empty for the vault itself
+    pub folder: String,
+    pub daily: String,
+    pub weekly: String,
+    pub monthly: String,
+    // vault relative templates a new note starts from
+    pub daily_template: Option<PathBuf>,
+    pub weekly_template: Option<PathBuf>,
+    pub monthly_template: Option<PathBuf>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Journal {
+            folder: "journal".to_string(),
+            daily: "%Y-%m-%d".to_string(),
+            weekly: "%G-W%V".to_string(),
+            monthly: "%Y-%m".to_string(),
+            daily_template: None,
+            weekly_template: None,
+            monthly_template: None,
+        }
+    }
+}
+
+// single key bindings for the list and the note's normal mode
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Keymap {
+    pub quit: char,
+    pub new_note: char,
+    pub search: char,
+    pub text_search: char,
+    // pressed with ctrl, from the list or the note
+    pub finder: char,
+    pub tag_tree: char,
+    pub agenda: char,
+    // open or create this period's journal note
+    pub daily: char,
+    pub weekly: char,
+    pub monthly: char,
+    pub save: char,
+    pub insert: char,
+    pub edit_title: char,
+    pub search_tags: char,
+    pub backlinks: char,
+    pub wrap: char,
+    pub external_editor: char,
+    pub next_match: char,
+    pub prev_match: char,
+    pub undo: char,
+    pub toggle_task: char,
+    // the journal note before or after the open one
+    pub prev_period: char,
+    pub next_period: char,
+    // pressed with ctrl
+    pub redo: char,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            quit: 'q',
+            new_note: 'n',
+            search: 's',
+            text_search: '/',
+            finder: 'p',
+            tag_tree: 't',
+            agenda: 'a',
+            daily: 'd',
+            weekly: 'w',
+            monthly: 'm',
+            save: 's',
+            insert: 'i',
+            edit_title: 't',
+            search_tags: 'T',
+            backlinks: 'B',
+            wrap: 'W',
+            external_editor: 'E',
+            next_match: 'n',
+            prev_match: 'N',
+            undo: 'u',
+            toggle_task: 'x',
+            prev_period: '[',
+            next_period: ']',
+            redo: 'r',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    // the vault
+    pub path: PathBuf,
+    // folder under the working directory used by the local list
+    pub local_dir: String,
+    pub extension: String,
+    pub sort: Sort,
+    // soft wrap long lines in the editor, toggled per session from there
+    pub wrap: bool,
+    // milliseconds without a key press before edits are saved, off when unset
+    pub autosave: Option<u64>,
+    // folder under the vault holding templates for new notes
+    pub templates: String,
+    pub journal: Journal,
+    pub theme: Theme,
+    pub keymap: Keymap,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            path: PathBuf::new(),
+            local_dir: "notes".to_string(),
+            extension: "md".to_string(),
+            sort: Sort::default(),
+            wrap: true,
+            autosave: None,
+            templates: "templates".to_string(),
+            journal: Journal::default(),
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+// widgets render through ratatui's Widget trait, which has no room for settings
+// replaced whole whenever settings load, so a new config brings its colours with it
+static THEME: RwLock<Option<Arc<Theme>>> = RwLock::new(None);
+
+pub fn theme() -> Arc<Theme> {
+    let theme = THEME.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    theme.clone().unwrap_or_default()
+}
+
+impl Settings {
+    pub fn config_dir() -> Result<PathBuf> {
+        let home = std::env::home_dir().ok_or(Error::NoHome)?;
+        Ok(home.join(".config").join("noter"))
+    }
+
+    // read ~/.config/noter/config in whichever format it was written
+    pub fn load() -> Result<Settings> {
+        let dir = Settings::config_dir()?;
+        if !Settings::exists(&dir) {
+            return Err(Error::NoConfig(dir.join("config.toml")));
+        }
+        let settings: Settings = Config::builder()
+            .add_source(config::File::with_name(&dir.join("config").to_string_lossy()))
+            .build()?
+            .try_deserialize()?;
+        settings.validate()
+    }
+
+    // first run, write a config pointing at the chosen vault
+    pub fn create(path: PathBuf) -> Result<Settings> {
+        let settings = Settings {
+            path,
+            ..Default::default()
+        };
+        fs::create_dir_all(&settings.path).map_err(|err| Error::io(&settings.path, err))?;
+        let settings = settings.validate()?;
+        let dir = Settings::config_dir()?;
+        fs::create_dir_all(&dir).map_err(|err| Error::io(&dir, err))?;
+        let file = dir.join("config.toml");
+        let contents = toml::to_string(&settings).map_err(|err| Error::InvalidSetting {
+            key: "config",
+            reason: err.to_string(),
+        })?;
+        fs::write(&file, contents).map_err(|err| Error::io(&file, err))?;
+        Ok(settings)
+    }
+
+    // make the theme visible to widgets, call once settings are final
+    pub fn publish_theme(&self) {
+        let mut theme = THEME.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *theme = Some(Arc::new(self.theme.clone()));
+    }
+
+    fn exists(dir: &Path) -> bool {
+        fs::read_dir(dir).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.path().file_stem().is_some_and(|stem| stem == "config"))
+        })
+    }
+
+    fn validate(mut self) -> Result<Self> {
+        let invalid = |key, reason: &str| Error::InvalidSetting {
+            key,
+            reason: reason.to_string(),
+        };
+        if self.path.as_os_str().is_empty() {
+            return Err(invalid("path", "is not set"));
+        }
+        if !self.path.is_dir() {
+            return Err(invalid(
+                "path",
+                &format!("`{}` is not a directory", self.path.display()),
+            ));
+        }
+        self.extension = self.extension.trim_start_matches('.').to_string();
+        if self.extension.is_empty() {
+            return Err(invalid("extension", "is empty"));
+        }
+        if self.local_dir.is_empty() || self.local_dir.contains(['/', '\\']) {
+            return Err(invalid("local_dir", "must be a single folder name"));
+        }
+        if Path::new(&self.templates).is_absolute() {
+            return Err(invalid("templates", "must be inside the vault"));
+        }
+        if Path::new(&self.journal.folder).is_absolute() {
+            return Err(invalid("journal.folder", "must be inside the vault"));
+        }
+        // titles have to be file names that read back into the same date
+        let sample = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap_or_default();
+        for (key, period) in [
+            ("journal.daily", Period::Daily),
+            ("journal.weekly", Period::Weekly),
+            ("journal.monthly", Period::Monthly),
+        ] {
+            let format = period.format(&self.journal);
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(invalid(key, &format!("`{}` is not a date format", format)));
+            }
+            let title = period.title(sample, &self.journal);
+            if title.is_empty() || title.contains(['/', '\\']) {
+                return Err(invalid(key, "must make a file name without slashes"));
+            }
+            if period.parse(&title, &self.journal) != Some(period.start(sample)) {
+                return Err(invalid(key, &format!("`{}` can't be read back into a date", title)));
+            }
+        }
+        if !code::has_theme(&self.theme.syntax) {
+            return Err(invalid(
+                "theme.syntax",
+                &format!("`{}` is not a bundled colour scheme", self.theme.syntax),
+            ));
+        }
+        let keymap = &self.keymap;
+        let note_keys = [
+            keymap.quit,
+            keymap.save,
+            keymap.insert,
+            keymap.edit_title,
+            keymap.search_tags,
+            keymap.backlinks,
+            keymap.wrap,
+            keymap.external_editor,
+            keymap.next_match,
+            keymap.prev_match,
+            keymap.undo,
+            keymap.toggle_task,
+            keymap.prev_period,
+            keymap.next_period,
+            keymap.redo,
+            // fixed in normal mode, Enter follows links
+            'g',
+            'G',
+            'w',
+            'b',
+            '\n',
+            '\r',
+        ];
+        let list_keys = [
+            keymap.quit,
+            keymap.new_note,
+            keymap.search,
+            keymap.text_search,
+            keymap.tag_tree,
+            keymap.agenda,
+            keymap.daily,
+            keymap.weekly,
+            keymap.monthly,
+            keymap.finder,
+        ];
+        for keys in [&note_keys[..], &list_keys[..]] {
+            if let Some(key) = keys
+                .iter()
+                .enumerate()
+                .find(|(i, key)| keys[..*i].contains(key))
+                .map(|(_, key)| key)
+            {
+                return Err(invalid(
+                    "keymap",
+                    &format!("`{}` is bound to more than one action", key),
+                ));
+            }
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,62 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::note::Note;
+
+// unsaved edits kept beside the note as .Title.md.swp, hidden so the vault walk skips it
+pub fn path(root: &Path, extension: &str, note: &Note) -> PathBuf {
+    titled(root, extension, &note.folder, &note.title)
+}
+
+// the swap file for a title, a renamed note's old one included
+pub fn titled(root: &Path, extension: &str, folder: &Path, title: &str) -> PathBuf {
+    root.join(folder).join(format!(".{}.{}.swp", title, extension))
+}
+
+pub fn write(root: &Path, extension: &str, note: &Note) -> Result<()> {
+    let path = path(root, extension, note);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| Error::io(parent, err))?;
+    }
+    fs::write(&path, note.file_contents()).map_err(|err| Error::io(&path, err))
+}
+
+// a missing swap file is already gone, so only other failures count
+pub fn remove(root: &Path, extension: &str, note: &Note) -> Result<()> {
+    remove_at(&path(root, extension, note))
+}
+
+pub fn remove_at(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(Error::io(path, err)),
+        _ => Ok(()),
+    }
+}
+
+// every swap file under the vault, paired with the path of the note it belongs to
+pub fn find(root: &Path, extension: &str) -> Vec<(PathBuf, PathBuf)> {
+    let suffix = format!(".{}.swp", extension);
+    let mut found = vec![];
+    let Ok(entries) = fs::read_dir(root) else {
+        return found;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() && !name.starts_with('.') => {
+                found.extend(find(&entry.path(), extension))
+            }
+            Ok(kind) if kind.is_file() => {
+                let title = name.strip_prefix('.').and_then(|name| name.strip_suffix(&suffix));
+                if let Some(title) = title.filter(|title| !title.is_empty()) {
+                    let note = root.join(format!("{}.{}", title, extension));
+                    found.push((entry.path(), note));
+                }
+            }
+            _ => {}
+        }
+    }
+    found.sort();
+    found
+}
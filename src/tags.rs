@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::block::Title;
+use ratatui::widgets::{Block, Paragraph};
+use style::Styled;
+
+use crate::app::{App, CurrentFrame};
+use crate::note::Note;
+use crate::settings::{theme, Keymap};
+use crate::traits::ThisFrame;
+use crate::utils::{grapheme_len, render_scrollbar, RcRc};
+
+// a row of the tag tree, paths have no # and nest with /
+#[derive(Debug, Clone)]
+pub struct TagEntry {
+    pub path: String,
+    pub depth: usize,
+    // notes tagged here or anywhere below
+    pub count: usize,
+    pub has_children: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TagTree {
+    pub entries: Vec<TagEntry>,
+    pub index: usize,
+    pub scroll: usize,
+    pub collapsed: HashSet<String>,
+    pub is_active: bool,
+}
+
+impl ThisFrame for TagTree {
+    fn get_instructions(&self, _keymap: &Keymap) -> Title<'_> {
+        Title::from(Line::from(vec![
+            " Back ".into(),
+            "<esc>".blue().bold(),
+            " Scroll Up ".into(),
+            "<UP>".blue().bold(),
+            " Scroll Down ".into(),
+            "<DOWN>".blue().bold(),
+            " Fold ".into(),
+            "<LEFT/RIGHT>".blue().bold(),
+            " Filter List ".into(),
+            "<return>".blue().bold(),
+        ]))
+    }
+
+    fn get_type(&self) -> String {
+        "Tags".to_string()
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => {
+                app.tag_tree.is_active = false;
+                app.note_list.is_active = true;
+                app.current_frame = CurrentFrame::List;
+            }
+            KeyCode::Up => {
+                if self.index == 0 {
+                    app.tag_tree.index = self.entries.len().saturating_sub(1);
+                } else {
+                    app.tag_tree.index = self.index - 1;
+                }
+            }
+            KeyCode::Down => {
+                if self.index + 1 >= self.entries.len() {
+                    app.tag_tree.index = 0;
+                } else {
+                    app.tag_tree.index = self.index + 1;
+                }
+            }
+            KeyCode::Left | KeyCode::Right => {
+                let Some(entry) = self.entries.get(self.index) else {
+                    return;
+                };
+                if key_event.code == KeyCode::Left {
+                    app.tag_tree.collapsed.insert(entry.path.to_lowercase());
+                } else {
+                    app.tag_tree.collapsed.remove(&entry.path.to_lowercase());
+                }
+                app.tag_tree.entries = TagTree::build(&app.note_list.notes, &app.tag_tree.collapsed);
+                app.tag_tree.index = app.tag_tree.index.min(app.tag_tree.entries.len().saturating_sub(1));
+            }
+            // a parent tag takes in everything nested under it
+            KeyCode::Enter => {
+                let Some(entry) = self.entries.get(self.index) else {
+                    return;
+                };
+                let search = format!("tag:{}", entry.path);
+                app.cursor_column = grapheme_len(&search);
+                app.note_list.search = Some(search);
+                app.note_list.is_search = true;
+                app.note_list.full_text = false;
+                app.note_list.index = 0;
+                app.note_list.is_active = true;
+                app.tag_tree.is_active = false;
+                app.highlight = None;
+                app.current_frame = CurrentFrame::List;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl TagTree {
+    pub fn new(notes: &[RcRc<Note>]) -> Self {
+        TagTree {
+            entries: TagTree::build(notes, &HashSet::new()),
+            index: 0,
+            scroll: 0,
+            collapsed: HashSet::new(),
+            is_active: true,
+        }
+    }
+
+    // every tag in the vault with its ancestors, in path order, skipping folded branches
+    pub fn build(notes: &[RcRc<Note>], collapsed: &HashSet<String>) -> Vec<TagEntry> {
+        // keyed by lowercase path parts so children sort straight after their parent
+        // and tags differing only in case share a row
+        let mut counts: BTreeMap<Vec<String>, (String, usize)> = BTreeMap::new();
+        for note in notes.iter() {
+            let note = note.borrow();
+            let mut seen: HashSet<Vec<String>> = HashSet::new();
+            for tag in note.tags.iter().flatten() {
+                let path = tag.path();
+                let parts: Vec<&str> = path.split('/').collect();
+                for depth in 1..=parts.len() {
+                    let prefix = parts[..depth].join("/");
+                    let key: Vec<String> =
+                        parts[..depth].iter().map(|part| part.to_lowercase()).collect();
+                    if !prefix.is_empty() && seen.insert(key.clone()) {
+                        counts.entry(key).or_insert_with(|| (prefix, 0)).1 += 1;
+                    }
+                }
+            }
+        }
+        let keys: Vec<&Vec<String>> = counts.keys().collect();
+        let mut entries = vec![];
+        for (position, (key, (path, count))) in counts.iter().enumerate() {
+            let hidden = (1..key.len()).any(|depth| collapsed.contains(&key[..depth].join("/")));
+            if hidden {
+                continue;
+            }
+            let has_children = keys
+                .get(position + 1)
+                .is_some_and(|next| next.len() > key.len() && next.starts_with(key));
+            entries.push(TagEntry {
+                path: path.clone(),
+                depth: key.len() - 1,
+                count: *count,
+                has_children,
+            });
+        }
+        entries
+    }
+}
+
+impl Widget for &TagTree {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let title = Title::from(format!(" Tags ({}) ", self.entries.len()).fg(theme().text).bold());
+        let block = Block::bordered()
+            .title(title.alignment(Alignment::Center))
+            .border_set(symbols::border::ROUNDED)
+            .set_style(theme().active);
+        let lines: Vec<Line> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(count, entry)| {
+                let colour = if self.index == count {
+                    theme().selected
+                } else {
+                    theme().tag
+                };
+                let folded = self.collapsed.contains(&entry.path.to_lowercase());
+                let marker = match (entry.has_children, folded) {
+                    (false, _) => "  ",
+                    (true, true) => "▸ ",
+                    (true, false) => "▾ ",
+                };
+                let name = entry.path.rsplit('/').next().unwrap_or(&entry.path);
+                Line::from(vec![
+                    ("  ".repeat(entry.depth) + marker).into(),
+                    format!("#{}", name).fg(colour),
+                    format!(" ({})", entry.count).dim(),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines)
+            .left_aligned()
+            .block(block)
+            .scroll((self.scroll as u16, 0))
+            .render(area, buf);
+        render_scrollbar(area, buf, self.scroll, self.entries.len());
+    }
+}
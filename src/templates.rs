@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDateTime;
+use regex::{Captures, Regex};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::{Error, Result};
+use crate::file_reader::{list_files, parse_file, write_file};
+use crate::frontmatter::Frontmatter;
+use crate::note::Note;
+use crate::utils::{column_of, RcRc};
+
+// where the cursor goes once the note is open, taken out of the text
+const CURSOR: &str = "{{cursor}}";
+
+// the answers gathered for a template before the note is made, the title is asked first
+#[derive(Debug, Clone)]
+pub struct TemplateForm {
+    pub name: String,
+    pub text: String,
+    pub folder: PathBuf,
+    pub prompts: Vec<String>,
+    pub answers: Vec<String>,
+    pub input: String,
+    pub error: Option<String>,
+}
+
+impl TemplateForm {
+    pub fn new(name: String, text: String, folder: PathBuf) -> Self {
+        let mut prompts = vec!["Title".to_string()];
+        prompts.extend(prompts_in(&text));
+        TemplateForm {
+            name,
+            text,
+            folder,
+            prompts,
+            answers: vec![],
+            input: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn push(&self, c: char) -> Self {
+        TemplateForm {
+            input: self.input.clone() + &c.to_string(),
+            error: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn pop(&self) -> Self {
+        let mut graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        graphemes.pop();
+        TemplateForm {
+            input: graphemes.concat(),
+            error: None,
+            ..self.clone()
+        }
+    }
+
+    // take the input as the answer to the current prompt
+    pub fn answer(&self) -> Self {
+        let input = self.input.trim();
+        let error = match self.answers.len() {
+            0 if input.is_empty() => Some("the title can't be empty"),
+            0 if input.contains(['/', '\\']) => Some("the title can't hold a slash"),
+            _ => None,
+        };
+        if let Some(error) = error {
+            return TemplateForm {
+                error: Some(error.to_string()),
+                ..self.clone()
+            };
+        }
+        let mut answers = self.answers.clone();
+        answers.push(input.to_string());
+        TemplateForm {
+            answers,
+            input: String::new(),
+            error: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.answers.len() >= self.prompts.len()
+    }
+
+    pub fn title(&self) -> &str {
+        self.answers.first().map_or("", |title| title.as_str())
+    }
+
+    // custom prompt answers by label
+    pub fn values(&self) -> HashMap<String, String> {
+        self.prompts
+            .iter()
+            .zip(self.answers.iter())
+            .skip(1)
+            .map(|(prompt, answer)| (prompt.clone(), answer.clone()))
+            .collect()
+    }
+}
+
+// the template files in the vault's templates folder, none when it doesn't exist
+pub fn list(root: &Path, folder: &str, extension: &str) -> Vec<PathBuf> {
+    let mut templates = list_files(&root.join(folder), extension, &mut vec![]).unwrap_or_default();
+    templates.sort();
+    templates
+}
+
+static VARIABLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*(\w+)(?::([^}]*))?\s*\}\}").unwrap());
+
+// the {{prompt:Question}} labels of a template, each once and in order
+pub fn prompts_in(text: &str) -> Vec<String> {
+    let mut prompts: Vec<String> = vec![];
+    for found in VARIABLE.captures_iter(text) {
+        if let ("prompt", Some(label)) = (&found[1], found.get(2)) {
+            let label = label.as_str().trim().to_string();
+            if !label.is_empty() && !prompts.contains(&label) {
+                prompts.push(label);
+            }
+        }
+    }
+    prompts
+}
+
+// fill in {{date}}, {{time}}, {{title}} and {{prompt:...}}, dates and times take a strftime
+// format after a colon, anything unknown is left as written
+pub fn fill(text: &str, title: &str, when: NaiveDateTime, answers: &HashMap<String, String>) -> String {
+    let valid = |format: &str| !StrftimeItems::new(format).any(|item| item == Item::Error);
+    VARIABLE
+        .replace_all(text, |found: &Captures| {
+            let argument = found.get(2).map(|argument| argument.as_str().trim());
+            match (&found[1], argument) {
+                ("date", None) => when.format("%Y-%m-%d").to_string(),
+                ("time", None) => when.format("%H:%M").to_string(),
+                ("date" | "time", Some(format)) if valid(format) => when.format(format).to_string(),
+                ("title", None) => title.to_string(),
+                ("prompt", Some(label)) => answers.get(label).cloned().unwrap_or_default(),
+                ("cursor", None) => CURSOR.to_string(),
+                _ => found[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+// write a new note from a template and read it back, along with where the cursor starts
+pub fn create(
+    root: &Path,
+    extension: &str,
+    folder: &Path,
+    title: &str,
+    template: &str,
+    when: NaiveDateTime,
+    answers: &HashMap<String, String>,
+) -> Result<(RcRc<Note>, (usize, usize))> {
+    let filled = fill(template, title, when, answers);
+    // the cursor is found in the body, rows start after the frontmatter
+    let (_, body) = Frontmatter::parse(&filled);
+    let cursor = body.find(CURSOR).map_or((0, 0), |at| {
+        let start = body[..at].rfind('\n').map_or(0, |newline| newline + 1);
+        let line = &body[start..];
+        (body[..at].matches('\n').count(), column_of(line, at - start))
+    });
+    let mut note = Note {
+        title: title.to_string(),
+        text: filled.replace(CURSOR, ""),
+        folder: folder.to_path_buf(),
+        ..Note::create_note()
+    };
+    let path = note.file_path(root, extension);
+    if path.exists() {
+        let exists = io::Error::new(io::ErrorKind::AlreadyExists, "a note with this title already exists");
+        return Err(Error::io(&path, exists));
+    }
+    write_file(root, extension, &mut note)?;
+    Ok((parse_file(note.file_contents(), &path, root)?, cursor))
+}
@@ -1,16 +1,14 @@
-use std::fmt::Display;
-
 use crossterm::event::KeyEvent;
-use ratatui::{widgets::block::Title, Frame};
+use ratatui::widgets::block::Title;
 
-use crate::app::App;
+use crate::{app::App, settings::Keymap};
 
 
 
 pub trait ThisFrame {
-    fn get_instructions(&self) -> Title;
+    fn get_instructions(&self, keymap: &Keymap) -> Title<'_>;
     fn handle_key_event(&mut self,app: &mut App, key_event: KeyEvent);
 
-    fn new() -> Self;
-    fn get_type(&self) -> String ;
+    #[allow(dead_code)]
+    fn get_type(&self) -> String;
 }
@@ -1,4 +1,5 @@
 use std::io::{stdout, Result, Stdout};
+use std::panic;
 
 use crossterm::{terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}, ExecutableCommand};
 use ratatui::prelude::*;
@@ -7,13 +8,22 @@ use ratatui::prelude::*;
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
 pub fn init() -> Result<Tui> {
-    stdout().execute(EnterAlternateScreen).unwrap();
-    enable_raw_mode().unwrap();
+    stdout().execute(EnterAlternateScreen)?;
+    enable_raw_mode()?;
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 
 pub fn restore() -> Result<()> {
-    stdout().execute(LeaveAlternateScreen).unwrap();
-    disable_raw_mode().unwrap();
+    stdout().execute(LeaveAlternateScreen)?;
+    disable_raw_mode()?;
     Ok(())
 }
+
+// leave the alternate screen before a panic message is printed so the shell stays usable
+pub fn install_panic_hook() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        hook(info);
+    }));
+}
@@ -1,7 +1,79 @@
 use std::{cell::RefCell, rc::Rc};
 
+use ratatui::prelude::*;
+use ratatui::widgets::{Scrollbar, ScrollbarOrientation, ScrollbarState};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
 
 pub type RcRc<T> = Rc<RefCell<T>>;
 pub fn rc_rc<T>(t: T) -> RcRc<T> {
     Rc::new(RefCell::new(t))
 }
+
+// cursor columns count grapheme clusters, these map them onto bytes and screen cells
+
+// byte offset of the grapheme at `column`, the end of the line past the last one
+pub fn byte_index(line: &str, column: usize) -> usize {
+    line.grapheme_indices(true)
+        .nth(column)
+        .map_or(line.len(), |(index, _)| index)
+}
+
+// grapheme column holding the byte at `index`
+pub fn column_of(line: &str, index: usize) -> usize {
+    line.grapheme_indices(true)
+        .take_while(|(start, _)| *start < index)
+        .count()
+}
+
+pub fn grapheme_len(line: &str) -> usize {
+    line.graphemes(true).count()
+}
+
+// cells the first `column` graphemes take, measured per grapheme as ratatui does
+pub fn display_width(line: &str, column: usize) -> usize {
+    line.graphemes(true)
+        .take(column)
+        .map(|grapheme| grapheme.width())
+        .sum()
+}
+
+// first visible row of a viewport `height` rows tall once scrolled just enough to show `row`
+pub fn scroll_into_view(scroll: usize, row: usize, height: usize) -> usize {
+    if row < scroll {
+        row
+    } else if height > 0 && row >= scroll + height {
+        row + 1 - height
+    } else {
+        scroll
+    }
+}
+
+// a scrollbar down the right border of a bordered block, drawn only when the content overflows
+pub fn render_scrollbar(area: Rect, buf: &mut Buffer, scroll: usize, rows: usize) {
+    let height = area.height.saturating_sub(2) as usize;
+    if rows <= height {
+        return;
+    }
+    let mut state = ScrollbarState::new(rows - height).position(scroll);
+    Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .render(area.inner(Margin::new(0, 1)), buf, &mut state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrolls_just_enough() {
+        assert_eq!(scroll_into_view(5, 2, 10), 2);
+        assert_eq!(scroll_into_view(5, 14, 10), 5);
+        assert_eq!(scroll_into_view(5, 15, 10), 6);
+        assert_eq!(scroll_into_view(0, 30, 10), 21);
+        // nothing drawn yet, leave it where it is
+        assert_eq!(scroll_into_view(3, 30, 0), 3);
+    }
+}
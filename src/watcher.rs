@@ -0,0 +1,64 @@
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::error::{Error, Result};
+use crate::file_reader::is_template;
+
+// changes to files under the vault, notify sends them from its own thread and the run loop
+// drains them between key presses
+#[derive(Debug)]
+pub struct Watcher {
+    pub root: PathBuf,
+    // the templates folder, its files aren't notes
+    templates: String,
+    events: Receiver<notify::Result<Event>>,
+    // dropping it stops the watch
+    _watcher: RecommendedWatcher,
+}
+
+impl Watcher {
+    pub fn new(root: &Path, templates: &str) -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let failed = |err: notify::Error| Error::Watch {
+            path: root.to_path_buf(),
+            reason: err.to_string(),
+        };
+        let mut watcher = notify::recommended_watcher(sender).map_err(failed)?;
+        watcher.watch(root, RecursiveMode::Recursive).map_err(failed)?;
+        Ok(Watcher {
+            root: root.to_path_buf(),
+            templates: templates.to_string(),
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    // the note files touched since the last call, each once. hidden files and folders are left
+    // out, which covers swap files, the temp files notes are written through and .git
+    pub fn changed(&self, extension: &str) -> Vec<PathBuf> {
+        let mut paths = BTreeSet::new();
+        for event in self.events.try_iter().flatten() {
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            paths.extend(event.paths.into_iter().filter(|path| self.is_note(path, extension)));
+        }
+        paths.into_iter().collect()
+    }
+
+    fn is_note(&self, path: &Path, extension: &str) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let hidden = relative.components().any(|part| match part {
+            Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+            _ => false,
+        });
+        !hidden
+            && path.extension().is_some_and(|ext| ext == extension)
+            && !is_template(&self.root, &self.templates, path)
+    }
+}
@@ -0,0 +1,144 @@
+use ratatui::text::{Line, Span};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::utils::{byte_index, display_width, grapheme_len};
+
+// one screen row of note text, a grapheme column range of one text line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisualRow {
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+// split text into screen rows, wrapping at spaces when given a width
+pub fn visual_rows(text: &str, width: Option<usize>) -> Vec<VisualRow> {
+    let mut rows = vec![];
+    for (row, line) in text.split('\n').enumerate() {
+        match width {
+            Some(width) => rows.extend(
+                wrap_line(line, width)
+                    .into_iter()
+                    .map(|(start, end)| VisualRow { row, start, end }),
+            ),
+            None => rows.push(VisualRow {
+                row,
+                start: 0,
+                end: grapheme_len(line),
+            }),
+        }
+    }
+    rows
+}
+
+// the screen row a cursor sits on, a column at the end of a wrapped row belongs to the next
+pub fn cursor_row(rows: &[VisualRow], row: usize, column: usize) -> usize {
+    rows.iter()
+        .rposition(|visual| visual.row == row && visual.start <= column)
+        .unwrap_or(rows.len().saturating_sub(1))
+}
+
+// cells from the start of a screen row to the cursor
+pub fn cursor_x(line: &str, visual: VisualRow, column: usize) -> usize {
+    display_width(line, column) - display_width(line, visual.start)
+}
+
+// the column on a screen row nearest to `x` cells in, kept on the row when the line wraps past it
+pub fn column_at(line: &str, visual: VisualRow, x: usize, wraps_on: bool) -> usize {
+    let mut used = 0;
+    let mut column = visual.start;
+    for grapheme in line.graphemes(true).skip(visual.start).take(visual.end - visual.start) {
+        used += grapheme.width();
+        if used > x {
+            break;
+        }
+        column += 1;
+    }
+    if wraps_on {
+        column.min(visual.end.saturating_sub(1)).max(visual.start)
+    } else {
+        column
+    }
+}
+
+// cut a styled line down to a grapheme column range
+pub fn slice_line<'a>(line: &Line<'a>, start: usize, end: usize) -> Line<'a> {
+    let mut spans = vec![];
+    let mut column = 0;
+    for span in line.spans.iter() {
+        let len = grapheme_len(&span.content);
+        let (from, to) = (start.max(column), end.min(column + len));
+        if from < to {
+            let content = &span.content;
+            let text = &content[byte_index(content, from - column)..byte_index(content, to - column)];
+            spans.push(Span::styled(text.to_string(), span.style));
+        }
+        column += len;
+    }
+    Line::from(spans).style(line.style)
+}
+
+// grapheme column ranges of a line wrapped at its last space before `width` cells
+fn wrap_line(line: &str, width: usize) -> Vec<(usize, usize)> {
+    let width = width.max(1);
+    let graphemes: Vec<&str> = line.graphemes(true).collect();
+    let widths: Vec<usize> = graphemes.iter().map(|grapheme| grapheme.width()).collect();
+    let mut ranges = vec![];
+    let mut start = 0;
+    let mut used = 0;
+    // column just past the last space on this row
+    let mut space = None;
+    for (column, grapheme) in graphemes.iter().enumerate() {
+        if used + widths[column] > width && column > start {
+            let end = match space {
+                Some(space) if space > start => space,
+                _ => column,
+            };
+            ranges.push((start, end));
+            used = widths[end..column].iter().sum();
+            start = end;
+            space = None;
+        }
+        used += widths[column];
+        if *grapheme == " " {
+            space = Some(column + 1);
+        }
+    }
+    ranges.push((start, graphemes.len()));
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_at_spaces() {
+        assert_eq!(wrap_line("hello world foo", 8), [(0, 6), (6, 12), (12, 15)]);
+        assert_eq!(wrap_line("abcdefghij", 4), [(0, 4), (4, 8), (8, 10)]);
+        assert_eq!(wrap_line("", 4), [(0, 0)]);
+    }
+
+    #[test]
+    fn wraps_by_display_width() {
+        assert_eq!(wrap_line("日本語", 4), [(0, 2), (2, 3)]);
+        assert_eq!(wrap_line("e\u{301}e\u{301}e\u{301}", 2), [(0, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn cursor_on_wrapped_rows() {
+        let line = "hello world foo";
+        let rows = visual_rows(&(line.to_string() + "\nx"), Some(8));
+        assert_eq!(rows.len(), 4);
+        assert_eq!(cursor_row(&rows, 0, 5), 0);
+        assert_eq!(cursor_row(&rows, 0, 6), 1);
+        assert_eq!(cursor_row(&rows, 0, 15), 2);
+        assert_eq!(cursor_row(&rows, 1, 0), 3);
+        assert_eq!(cursor_x(line, rows[1], 9), 3);
+        assert_eq!(column_at(line, rows[1], 3, true), 9);
+        assert_eq!(column_at(line, rows[1], 100, true), 11);
+        assert_eq!(column_at(line, rows[1], 100, false), 12);
+        assert_eq!(visual_rows("ab\ncd", None).len(), 2);
+    }
+}